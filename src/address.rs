@@ -0,0 +1,137 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Parsers for the address arguments accepted on the command line. Every address can be given
+//! with its type prefix (`component_…`, `resource_…`, `vault_…`) or as bare hex. Bad input is
+//! rejected while the command line is parsed, before anything is sent to the daemon, and is
+//! reported as an invalid address.
+
+use crate::error::ClientError;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+use tari_utilities::hex::from_hex;
+
+const COMPONENT_PREFIX: &str = "component_";
+const RESOURCE_PREFIX: &str = "resource_";
+const VAULT_PREFIX: &str = "vault_";
+
+/// Length of the hex encoding of a 32 byte address
+const ADDRESS_HEX_LEN: usize = 64;
+
+/// Why a value is not an address. It has its own type so that the command line can tell a bad
+/// address apart from other arguments it could not parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressError(String);
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for AddressError {}
+
+impl From<AddressError> for String {
+    fn from(err: AddressError) -> Self {
+        err.0
+    }
+}
+
+pub fn parse_component_address(value: &str) -> Result<ComponentAddress, AddressError> {
+    let hex = value.strip_prefix(COMPONENT_PREFIX).unwrap_or(value);
+    ComponentAddress::from_str(&format!("{}{}", COMPONENT_PREFIX, hex)).map_err(|_| {
+        AddressError(format!(
+            "'{}' is not a component address, expected component_<hex> or <hex>",
+            value
+        ))
+    })
+}
+
+pub fn parse_resource_address(value: &str) -> Result<ResourceAddress, AddressError> {
+    let hex = value.strip_prefix(RESOURCE_PREFIX).unwrap_or(value);
+    ResourceAddress::from_str(&format!("{}{}", RESOURCE_PREFIX, hex)).map_err(|_| {
+        AddressError(format!(
+            "'{}' is not a resource address, expected resource_<hex> or <hex>",
+            value
+        ))
+    })
+}
+
+pub fn parse_vault_id(value: &str) -> Result<VaultId, AddressError> {
+    let hex = value.strip_prefix(VAULT_PREFIX).unwrap_or(value);
+    VaultId::from_hex(hex).map_err(|_| {
+        AddressError(format!(
+            "'{}' is not a vault id, expected vault_<hex> or <hex>",
+            value
+        ))
+    })
+}
+
+pub fn parse_transaction_id(value: &str) -> Result<TransactionId, AddressError> {
+    from_hex(value)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(TransactionId::new)
+        .ok_or_else(|| {
+            AddressError(format!(
+                "'{}' is not a transaction id, expected {} hex characters",
+                value, ADDRESS_HEX_LEN
+            ))
+        })
+}
+
+/// An account argument: either a component address, or a name to be looked up in the address
+/// book or the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountArg {
+    Address(ComponentAddress),
+    Name(String),
+}
+
+impl AccountArg {
+    /// Anything that looks like an address must be a valid one; everything else is taken to be
+    /// a name.
+    pub fn parse(value: &str) -> Result<Self, AddressError> {
+        let looks_like_address = value.starts_with(COMPONENT_PREFIX)
+            || (value.len() == ADDRESS_HEX_LEN && value.chars().all(|c| c.is_ascii_hexdigit()));
+        if looks_like_address {
+            return parse_component_address(value).map(Self::Address);
+        }
+        if value.is_empty()
+            || !value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+        {
+            return Err(AddressError(format!(
+                "'{}' is not a component address, alias or account name",
+                value
+            )));
+        }
+        Ok(Self::Name(value.to_string()))
+    }
+
+    /// The address of the account. Names must have been resolved before calling this.
+    pub fn address(&self) -> Result<ComponentAddress, ClientError> {
+        match self {
+            Self::Address(address) => Ok(*address),
+            Self::Name(name) => Err(ClientError::invalid_address(
+                name.as_str(),
+                "account name was not resolved to an address",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AccountArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(address) => write!(f, "{}", address),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+/// Formats a raw token amount with `decimals` decimal places, e.g. 1250 with 2 decimals as
+/// `12.50`.
+pub fn format_amount(value: i64, decimals: u32) -> String {
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let digits = value.unsigned_abs().to_string();
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{}{}.{}", sign, whole, fraction)
+}
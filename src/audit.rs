@@ -0,0 +1,182 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Reconciles the coin's total supply with the coins that can be found on the network.
+//!
+//! Coins are looked for in the vaults of the components we know about (see [`IndexerClient`]),
+//! so coins held anywhere else show up as a discrepancy.
+//!
+//! A blacklisted user's coins are the coins in the account their badge was recalled from,
+//! which only the local journal records.
+
+use crate::client::component_address;
+use crate::client::resource_address;
+use crate::config::Deployment;
+use crate::error::ClientError;
+use crate::indexer::IndexerClient;
+use crate::journal::JournalAction;
+use crate::journal::JournalEntry;
+use crate::users::blacklisted_users;
+use crate::users::user_id;
+use std::collections::BTreeMap;
+use tari_engine_types::substate::SubstateAddress;
+use tari_engine_types::substate::SubstateValue;
+use tari_template_lib::models::Amount;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::VaultId;
+
+/// A vault of `coin_resource` as of the version that was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinVault {
+    pub component: ComponentAddress,
+    pub vault: VaultId,
+    pub version: u32,
+    pub balance: i64,
+    /// Whether the component is the account of a blacklisted user
+    pub blacklisted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SupplyAudit {
+    pub total_supply: i64,
+    /// The coin component's own vaults
+    pub treasury: Vec<CoinVault>,
+    /// The coin vaults of the other components searched, ordered by component
+    pub holdings: Vec<CoinVault>,
+    /// Blacklisted users whose account could not be found, so their coins are not counted as
+    /// blacklisted
+    pub unresolved_blacklisted: Vec<NonFungibleId>,
+    /// How many components were searched, including the coin component
+    pub components_searched: usize,
+}
+
+impl SupplyAudit {
+    pub fn treasury_balance(&self) -> i64 {
+        self.treasury.iter().map(|v| v.balance).sum()
+    }
+
+    pub fn holdings_balance(&self) -> i64 {
+        self.holdings.iter().map(|v| v.balance).sum()
+    }
+
+    /// The coins found: the treasury and every holding.
+    pub fn accounted(&self) -> i64 {
+        self.treasury_balance() + self.holdings_balance()
+    }
+
+    /// How many more coins exist than were found. Negative if more were found than exist.
+    pub fn discrepancy(&self) -> i64 {
+        self.total_supply - self.accounted()
+    }
+
+    pub fn blacklisted_balance(&self) -> i64 {
+        self.holdings
+            .iter()
+            .filter(|v| v.blacklisted)
+            .map(|v| v.balance)
+            .sum()
+    }
+
+    /// The `limit` components holding the most coins outside the treasury, most first. Equal
+    /// balances are ordered by address so the report is the same every time.
+    pub fn top_holders(&self, limit: usize) -> Vec<(ComponentAddress, i64)> {
+        let mut balances = BTreeMap::new();
+        for vault in &self.holdings {
+            *balances.entry(vault.component).or_insert(0) += vault.balance;
+        }
+        let mut holders: Vec<_> = balances
+            .into_iter()
+            .filter(|(_, balance)| *balance != 0)
+            .collect();
+        holders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        holders.truncate(limit);
+        holders
+    }
+}
+
+/// Reads every coin vault of the coin component, of `components` and of the accounts in the
+/// journal from the indexer and compares them with `total_supply`. The journal is also used to
+/// find the accounts of blacklisted users.
+pub async fn audit_supply(
+    indexer: &IndexerClient,
+    deployment: &Deployment,
+    total_supply: Amount,
+    components: &[ComponentAddress],
+    journal: &[JournalEntry],
+) -> Result<SupplyAudit, ClientError> {
+    let coin_component = component_address(deployment.coin_component()?)?;
+    let coin_resource = resource_address(deployment.coin_resource()?)?;
+
+    // Accounts blacklisted by account are searched too, whether or not they are known otherwise
+    let journal_accounts = journal
+        .iter()
+        .filter_map(|entry| entry.account.as_deref())
+        .filter_map(|account| component_address(account).ok());
+    let mut searched: Vec<_> = components
+        .iter()
+        .copied()
+        .chain(journal_accounts)
+        .filter(|c| *c != coin_component)
+        .collect();
+    searched.sort();
+    searched.dedup();
+
+    // Every vault seen, to find the account a badge was recalled from
+    let mut owners = BTreeMap::new();
+    let mut treasury = vec![];
+    let mut holdings = vec![];
+    for component in std::iter::once(coin_component).chain(searched.iter().copied()) {
+        for vault_id in indexer.component_vault_ids(component).await? {
+            owners.insert(vault_id.to_string(), component);
+            let substate = indexer
+                .substate_version(SubstateAddress::Vault(vault_id), None)
+                .await?;
+            let SubstateValue::Vault(vault) = substate.value else {
+                return Err(ClientError::Indexer(format!("{} is not a vault", vault_id)));
+            };
+            if *vault.resource_address() != coin_resource {
+                continue;
+            }
+            let coin_vault = CoinVault {
+                component,
+                vault: vault_id,
+                version: substate.version,
+                balance: vault.balance().value(),
+                blacklisted: false,
+            };
+            if component == coin_component {
+                treasury.push(coin_vault);
+            } else {
+                holdings.push(coin_vault);
+            }
+        }
+    }
+
+    let mut unresolved_blacklisted = vec![];
+    for id in blacklisted_users(indexer, deployment).await?.into_keys() {
+        let entry = journal.iter().rev().find(|entry| {
+            entry.action == JournalAction::Blacklist && Some(entry.user_id) == user_id(&id)
+        });
+        let account = entry.and_then(|entry| match (&entry.account, &entry.vault) {
+            (Some(account), _) => component_address(account).ok(),
+            (None, Some(vault)) => owners.get(vault).copied(),
+            (None, None) => None,
+        });
+        match account {
+            Some(account) => holdings
+                .iter_mut()
+                .filter(|v| v.component == account)
+                .for_each(|v| v.blacklisted = true),
+            None => unresolved_blacklisted.push(id),
+        }
+    }
+
+    Ok(SupplyAudit {
+        total_supply: total_supply.value(),
+        treasury,
+        holdings,
+        unresolved_blacklisted,
+        components_searched: searched.len() + 1,
+    })
+}
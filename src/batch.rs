@@ -0,0 +1,142 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! The progress of a `batch-send`, kept in a state file next to the CSV so that an interrupted
+//! batch can be run again without paying anyone twice.
+
+use crate::error::ClientError;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// One row of a payout CSV: `recipient,amount`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRow {
+    /// Account address, alias or wallet account name
+    pub recipient: String,
+    pub amount: u64,
+}
+
+/// Reads the rows of a payout CSV, which must have a `recipient,amount` header.
+pub fn read_rows(path: &Path) -> Result<Vec<PaymentRow>, ClientError> {
+    let invalid =
+        |e: csv::Error| ClientError::invalid_argument("csv", format!("{}: {}", path.display(), e));
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(invalid)?;
+    let rows = reader
+        .deserialize()
+        .collect::<Result<Vec<PaymentRow>, _>>()
+        .map_err(invalid)?;
+    if rows.is_empty() {
+        return Err(ClientError::invalid_argument(
+            "csv",
+            format!("{} has no payments", path.display()),
+        ));
+    }
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    /// Not sent yet
+    Pending,
+    /// About to be submitted. If a batch stops here it is not known whether the transaction
+    /// reached the daemon
+    Submitting,
+    /// Submitted but not known to be finalized
+    Submitted,
+    Paid,
+    /// Rejected, or not sent because it would have been rejected. Failed rows are retried
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    #[serde(flatten)]
+    pub row: PaymentRow,
+    pub status: PaymentStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchState {
+    /// The address of the account paying, resolved from the alias or account name if one was
+    /// given on the command line
+    pub from: String,
+    pub payments: Vec<Payment>,
+}
+
+impl BatchState {
+    pub fn new(from: String, rows: Vec<PaymentRow>) -> Self {
+        Self {
+            from,
+            payments: rows
+                .into_iter()
+                .map(|row| Payment {
+                    row,
+                    status: PaymentStatus::Pending,
+                    transaction_id: None,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The state file used for the batch in `csv` unless another is given.
+    pub fn default_path(csv: &Path) -> PathBuf {
+        let mut name = csv.file_name().unwrap_or_default().to_os_string();
+        name.push(".state.json");
+        csv.with_file_name(name)
+    }
+
+    /// Loads the state of an earlier run, or None if the batch has not been started.
+    pub fn load(path: &Path) -> Result<Option<Self>, ClientError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&contents).map(Some).map_err(|e| {
+            ClientError::invalid_argument(
+                "state",
+                format!("{} is not a batch state file: {}", path.display(), e),
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ClientError> {
+        let contents = serde_json::to_string_pretty(self).expect("batch state is serializable");
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Checks that an earlier run was for the same payer and rows, so a state file is never
+    /// applied to a CSV that has been edited since.
+    pub fn check_matches(&self, from: &str, rows: &[PaymentRow]) -> Result<(), ClientError> {
+        if self.from != from {
+            return Err(ClientError::invalid_argument(
+                "from",
+                format!("the batch was started paying from {}", self.from),
+            ));
+        }
+        let started: Vec<_> = self.payments.iter().map(|p| &p.row).collect();
+        if started.len() != rows.len() || started.iter().zip(rows).any(|(a, b)| *a != b) {
+            return Err(ClientError::invalid_argument(
+                "csv",
+                concat!(
+                    "the file has changed since the batch was started; ",
+                    "finish or remove the old state file first"
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,75 +1,211 @@
 //  Copyright 2023 The Tari Project
 //  SPDX-License-Identifier: BSD-3-Clause
 
+use crate::output;
+use crate::output::OutputFormat;
+use clap::error::ContextKind;
+use clap::error::ContextValue;
+use clap::error::ErrorKind;
+use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use tari_engine_types::parse_arg;
+use tari_engine_types::TemplateAddress;
+use tari_stable_coin_example::address::AccountArg;
+use tari_stable_coin_example::address::AddressError;
+use tari_stable_coin_example::client::component_address;
+use tari_stable_coin_example::config::Profile;
+use tari_stable_coin_example::users::user_id;
+use tari_stable_coin_example::ClientError;
+use tari_stable_coin_example::DaemonClient;
+use tari_template_lib::args::Arg;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_utilities::hex::from_hex;
 
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
 #[clap(propagate_version = true)]
 pub(crate) struct Cli {
+    /// Config file to use instead of searching for stablecoin.toml
+    #[clap(long, env = "STABLECOIN_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Profile from the config file to use
+    #[clap(long, short = 'p', env = "STABLECOIN_PROFILE")]
+    pub profile: Option<String>,
     #[clap(long, short = 'e', alias = "endpoint", env = "JRPC_ENDPOINT")]
     pub daemon_jrpc_endpoint: Option<String>,
+    #[clap(long, env = "INDEXER_JRPC_ENDPOINT")]
+    pub indexer_endpoint: Option<String>,
     #[clap(long, short = 't', alias = "token")]
     pub auth_token: Option<String>,
-    #[clap(
-        long,
-        alias = "template_address",
-        default_value = "0x6c658001d3c8587b194990ea0ffd643c38685d5d7d13ef398ac62875c79fbab4"
-    )]
-    pub template: String,
+    #[clap(long, alias = "template_address", env = "STABLECOIN_TEMPLATE")]
+    pub template: Option<String>,
     #[clap(long, short = 'd')]
     pub dump_buckets: bool,
     #[clap(long)]
     pub dry_run: bool,
+    /// Return as soon as the transaction is submitted instead of waiting for it to finalize
+    #[clap(long)]
+    pub no_wait: bool,
+    /// Write the transaction to this file for `submit-prepared` instead of submitting it
+    #[clap(long, value_name = "FILE")]
+    pub prepare: Option<PathBuf>,
+    /// How many seconds to wait for a submitted transaction to finalize
+    #[clap(long, default_value = "120")]
+    pub wait_timeout: u64,
+    /// How to print the result of the command
+    #[clap(long, short = 'o', value_enum, default_value = "text", global = true)]
+    pub output: OutputFormat,
     #[clap(subcommand)]
     pub command: Command,
-    #[clap(long, short = 'f', default_value = "1500")]
-    pub max_fee: u64,
-    #[clap(long, short = 'a', default_value = "TestAccount_0")]
-    pub default_account: String,
-    #[clap(
-        long,
-        default_value = "component_ea50fa2002a7898eef13a851ed41cacc713b6bf15c5ab4530d1ac5acc4d06263"
-    )]
-    pub default_coin_component: String,
-    #[clap(
-        long,
-        default_value = "resource_bc5ab4d0974aa8347d5060cf20f9f0b0b415bfb45559f6d94e913d1cfb04b614"
-    )]
-    pub admin_badge_resource: String,
-    #[clap(
-        long,
-        default_value = "resource_d7d57fd8a795d243f0d93d4e7e651bb210a0f0f990ceb242712b56f926b636e0"
-    )]
-    pub user_badge_resource: String,
-    #[clap(
-        long,
-        default_value = "resource_7af49ffcb972d90dd04a29ee32c099b46140d8c0422a7200c0c3118636a75f0d"
-    )]
-    pub coin_resource: String,
+    #[clap(long, short = 'f', env = "STABLECOIN_MAX_FEE")]
+    pub max_fee: Option<u64>,
+    #[clap(long, env = "STABLECOIN_DECIMALS")]
+    pub decimals: Option<u32>,
+    #[clap(long, env = "STABLECOIN_JOURNAL")]
+    pub journal: Option<PathBuf>,
+    #[clap(long, short = 'a', env = "STABLECOIN_ACCOUNT")]
+    pub default_account: Option<String>,
+    #[clap(long, env = "STABLECOIN_COIN_COMPONENT")]
+    pub default_coin_component: Option<String>,
+    #[clap(long, env = "STABLECOIN_ADMIN_BADGE_RESOURCE")]
+    pub admin_badge_resource: Option<String>,
+    #[clap(long, env = "STABLECOIN_USER_BADGE_RESOURCE")]
+    pub user_badge_resource: Option<String>,
+    #[clap(long, env = "STABLECOIN_COIN_RESOURCE")]
+    pub coin_resource: Option<String>,
 }
 
 impl Cli {
+    /// Parses the command line. One that does not parse is reported like any failed command, in
+    /// the requested output format, and the process exits.
     pub fn init() -> Self {
-        Self::parse()
+        Self::try_parse().unwrap_or_else(|err| process::exit(report_parse_error(err)))
+    }
+
+    /// Overrides the values in `profile` with anything given on the command line or in the
+    /// environment.
+    pub fn apply_to(&self, profile: &mut Profile) {
+        fn set<T: Clone>(target: &mut Option<T>, value: &Option<T>) {
+            if value.is_some() {
+                *target = value.clone();
+            }
+        }
+
+        set(&mut profile.endpoint, &self.daemon_jrpc_endpoint);
+        set(&mut profile.indexer, &self.indexer_endpoint);
+        set(&mut profile.account, &self.default_account);
+        set(&mut profile.max_fee, &self.max_fee);
+        set(&mut profile.decimals, &self.decimals);
+        set(&mut profile.journal, &self.journal);
+        let deployment = &mut profile.deployment;
+        set(&mut deployment.template, &self.template);
+        set(&mut deployment.coin_component, &self.default_coin_component);
+        set(
+            &mut deployment.admin_badge_resource,
+            &self.admin_badge_resource,
+        );
+        set(
+            &mut deployment.user_badge_resource,
+            &self.user_badge_resource,
+        );
+        set(&mut deployment.coin_resource, &self.coin_resource);
+    }
+}
+
+/// Reports a command line clap rejected and returns the exit code: a value one of the address
+/// parsers rejected is an invalid address, anything else an invalid argument. Requests for help
+/// or the version are printed as clap always prints them.
+fn report_parse_error(err: clap::Error) -> i32 {
+    if matches!(
+        err.kind(),
+        ErrorKind::DisplayHelp | ErrorKind::DisplayVersion
+    ) {
+        err.exit();
     }
+    let args: Vec<String> = env::args().collect();
+    let command = Cli::command()
+        .get_subcommands()
+        .find(|command| args.iter().any(|arg| arg == command.get_name()))
+        .map(|command| command.get_name().to_string())
+        .unwrap_or_default();
+    output::report(requested_format(&args), &command, Err(parse_error(&err)))
+}
+
+fn parse_error(err: &clap::Error) -> ClientError {
+    let context = |kind| match err.get(kind) {
+        Some(ContextValue::String(value)) => value.clone(),
+        _ => String::new(),
+    };
+    match err
+        .source()
+        .and_then(|source| source.downcast_ref::<AddressError>())
+    {
+        Some(reason) => {
+            ClientError::invalid_address(context(ContextKind::InvalidValue), reason.to_string())
+        }
+        None => {
+            let rendered = err.render().to_string();
+            let reason = rendered.lines().next().unwrap_or_default();
+            ClientError::invalid_argument(
+                context(ContextKind::InvalidArg),
+                reason.trim_start_matches("error: "),
+            )
+        }
+    }
+}
+
+/// The `--output` format asked for on a command line that did not parse as a whole.
+fn requested_format(args: &[String]) -> OutputFormat {
+    let mut format = OutputFormat::Text;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "-o" | "--output" => args.next().map(String::as_str),
+            arg => arg
+                .strip_prefix("--output=")
+                .or_else(|| arg.strip_prefix("-o")),
+        };
+        if let Some(value) = value.and_then(|value| OutputFormat::from_str(value, true).ok()) {
+            format = value;
+        }
+    }
+    format
 }
 
 #[derive(Debug, Subcommand, Clone)]
 pub(crate) enum Command {
     Login(login::Command),
 
+    PublishTemplate(publish_template::Command),
+
     Instantiate(instantiate::Command),
 
+    Bootstrap(bootstrap::Command),
+
     IncreaseSupply(increase_supply::Command),
 
     DecreaseSupply(decrease_supply::Command),
 
     TotalSupply(total_supply::Command),
 
+    Balance(balance::Command),
+
+    History(history::Command),
+
     Withdraw(withdraw::Command),
 
+    MintAndDistribute(mint_and_distribute::Command),
+
     Deposit(deposit::Command),
 
     CreateNewAdmin(create_new_admin::Command),
@@ -85,31 +221,310 @@ pub(crate) enum Command {
     SetUserData(set_user_data::Command),
 
     Send(send::Command),
+
+    BatchSend(batch_send::Command),
+
+    SubmitPrepared(submit_prepared::Command),
+
+    #[clap(subcommand)]
+    Users(users::Command),
+
+    #[clap(subcommand)]
+    Blacklist(blacklist::Command),
+
+    Trace(trace::Command),
+
+    #[clap(subcommand)]
+    Audit(audit::Command),
+
+    #[clap(subcommand)]
+    Alias(alias::Command),
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Login(_) => "login",
+            Command::PublishTemplate(_) => "publish-template",
+            Command::Instantiate(_) => "instantiate",
+            Command::Bootstrap(_) => "bootstrap",
+            Command::IncreaseSupply(_) => "increase-supply",
+            Command::DecreaseSupply(_) => "decrease-supply",
+            Command::TotalSupply(_) => "total-supply",
+            Command::Balance(_) => "balance",
+            Command::History(_) => "history",
+            Command::Withdraw(_) => "withdraw",
+            Command::MintAndDistribute(_) => "mint-and-distribute",
+            Command::Deposit(_) => "deposit",
+            Command::CreateNewAdmin(_) => "create-new-admin",
+            Command::CreateNewUser(_) => "create-new-user",
+            Command::BlacklistUser(_) => "blacklist-user",
+            Command::RemoveFromBlacklist(_) => "remove-from-blacklist",
+            Command::GetUserData(_) => "get-user-data",
+            Command::SetUserData(_) => "set-user-data",
+            Command::Send(_) => "send",
+            Command::BatchSend(_) => "batch-send",
+            Command::SubmitPrepared(_) => "submit-prepared",
+            Command::Users(_) => "users",
+            Command::Blacklist(_) => "blacklist",
+            Command::Trace(_) => "trace",
+            Command::Audit(_) => "audit",
+            Command::Alias(_) => "alias",
+        }
+    }
+
+    /// Replaces every account argument given by name with the address it names: an entry in
+    /// the address book, or failing that an account in the wallet.
+    pub async fn resolve_accounts(
+        &mut self,
+        aliases: &BTreeMap<String, String>,
+        client: &DaemonClient,
+    ) -> Result<(), ClientError> {
+        for account in self.account_args() {
+            let address = resolve_account(account, aliases, client).await?;
+            *account = AccountArg::Address(address);
+        }
+        Ok(())
+    }
+
+    fn account_args(&mut self) -> Vec<&mut AccountArg> {
+        match self {
+            Command::IncreaseSupply(com) => vec![&mut com.account_component_address],
+            Command::Withdraw(com) => vec![&mut com.admin_account_component, &mut com.into_account],
+            Command::MintAndDistribute(com) => {
+                let mut accounts = vec![&mut com.admin_account_component];
+                accounts.extend(com.distributions.iter_mut().map(|d| &mut d.account));
+                accounts
+            }
+            Command::CreateNewAdmin(com) => vec![
+                &mut com.admin_account_component,
+                &mut com.new_admin_component,
+            ],
+            Command::CreateNewUser(com) => vec![
+                &mut com.admin_account_component,
+                &mut com.send_to_user_component,
+            ],
+            Command::BlacklistUser(com) => {
+                let mut accounts = vec![&mut com.admin_account_component];
+                accounts.extend(com.account.as_mut());
+                accounts
+            }
+            Command::Send(com) => vec![&mut com.from_component, &mut com.to_component],
+            Command::Deposit(com) => vec![
+                &mut com.component_address,
+                &mut com.bucket_withdraw_from_component,
+            ],
+            Command::BatchSend(com) => vec![&mut com.from_component],
+            Command::Balance(com) => com.account.iter_mut().collect(),
+            Command::History(com) => com.account.iter_mut().collect(),
+            Command::Trace(com) => com.account.iter_mut().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// The address of an account given as an address, an entry in the address book or the name of
+/// an account in the wallet.
+pub(crate) async fn resolve_account(
+    account: &AccountArg,
+    aliases: &BTreeMap<String, String>,
+    client: &DaemonClient,
+) -> Result<ComponentAddress, ClientError> {
+    match account {
+        AccountArg::Address(address) => Ok(*address),
+        AccountArg::Name(name) => match aliases.get(name.as_str()) {
+            Some(address) => component_address(address),
+            None => client.account_address(name).await,
+        },
+    }
+}
+
+pub(crate) fn template_address(value: &str) -> Result<TemplateAddress, ClientError> {
+    from_hex(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            ClientError::invalid_address(value, "expected a hex encoded template address")
+        })
+}
+
+pub(crate) fn arg(name: &str, value: &str) -> Result<Arg, ClientError> {
+    parse_arg(value)
+        .map_err(|_| ClientError::invalid_argument(name, format!("could not parse '{}'", value)))
+}
+
+/// Writes `rows` to `path` as CSV with the given columns if it ends in `.csv`, otherwise as a
+/// JSON array. Nested values are written to CSV cells as JSON.
+pub(crate) fn export(
+    path: &Path,
+    columns: &[&str],
+    rows: &[serde_json::Value],
+) -> Result<(), ClientError> {
+    let is_csv = path
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("csv"));
+    if !is_csv {
+        let contents = serde_json::to_string_pretty(rows).expect("rows are serializable");
+        fs::write(path, contents)?;
+        return Ok(());
+    }
+
+    let mut writer = csv::Writer::from_path(path).map_err(std::io::Error::from)?;
+    writer.write_record(columns).map_err(std::io::Error::from)?;
+    for row in rows {
+        let cells = columns.iter().map(|column| match &row[*column] {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        });
+        writer.write_record(cells).map_err(std::io::Error::from)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The user id of a user badge as a number. Badges not minted by create-new-user may have ids
+/// that are not numbers, which are shown as they are.
+pub(crate) fn user_id_value(id: &NonFungibleId) -> serde_json::Value {
+    match user_id(id) {
+        Some(user_id) => user_id.into(),
+        None => id.to_string().into(),
+    }
+}
+
+/// The name to show for an account: its alias, else its name in the wallet, else its address.
+pub(crate) fn account_label(
+    address: &ComponentAddress,
+    aliases: &BTreeMap<String, String>,
+    wallet_accounts: &[(String, ComponentAddress)],
+) -> String {
+    let address_str = address.to_string();
+    aliases
+        .iter()
+        .find(|(_, alias)| **alias == address_str)
+        .map(|(name, _)| name.clone())
+        .or_else(|| {
+            wallet_accounts
+                .iter()
+                .find(|(_, account)| account == address)
+                .map(|(name, _)| name.clone())
+        })
+        .unwrap_or(address_str)
 }
 
 pub mod login {
-    use crate::daemon_client::DaemonClient;
     use clap::Args;
     use std::fs;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {}
 
     impl Command {
-        pub async fn run(self, mut client: DaemonClient) {
-            let token = client.login().await;
-            fs::write("token.data", token).unwrap();
+        pub async fn run(self, mut client: DaemonClient) -> Result<(), ClientError> {
+            let token = client.login().await?;
+            fs::write("token.data", token)?;
+            Ok(())
         }
     }
 }
 
-pub(crate) mod instantiate {
-    use crate::daemon_client::DaemonClient;
+pub(crate) mod publish_template {
+    use crate::output::CommandOutput;
     use clap::Args;
+    use std::fs;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::config::ConfigFile;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
 
+    use tari_engine_types::commit_result::TransactionResult;
     use tari_engine_types::instruction::Instruction;
-    use tari_engine_types::parse_arg;
+    use tari_engine_types::substate::SubstateAddress;
+
+    const WASM_MAGIC: &[u8] = b"\0asm";
+
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        /// The compiled template, e.g. target/wasm32-unknown-unknown/release/stable_coin.wasm
+        pub wasm_file: PathBuf,
+    }
+
+    impl Command {
+        pub async fn run(
+            self,
+            mut client: DaemonClient,
+            fees: u64,
+            config: &mut ConfigFile,
+            profile_name: &str,
+        ) -> Result<CommandOutput, ClientError> {
+            let binary = fs::read(&self.wasm_file)?;
+            if !binary.starts_with(WASM_MAGIC) {
+                return Err(ClientError::invalid_argument(
+                    "wasm_file",
+                    format!("{} is not a WASM module", self.wasm_file.display()),
+                ));
+            }
+
+            let outcome = client
+                .submit_instruction(
+                    Instruction::PublishTemplate { binary },
+                    false,
+                    false,
+                    fees,
+                    vec![],
+                )
+                .await?;
+
+            // Without a finalized result (e.g. with --no-wait) there is nothing to record
+            let diff = match outcome.result.as_ref().map(|r| &r.result) {
+                Some(TransactionResult::Accept(diff)) => diff,
+                _ => return Ok(outcome.into()),
+            };
+            let template = diff
+                .up_iter()
+                .find_map(|(address, _)| match address {
+                    SubstateAddress::Template(address) => Some(address.as_hash().to_string()),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    ClientError::InvalidResponse(
+                        "the publish transaction did not create a template".to_string(),
+                    )
+                })?;
+
+            let path = config.save_template(profile_name, template.clone())?;
+
+            Ok(
+                CommandOutput::from(outcome).with_return_values(serde_json::json!({
+                    "template": template,
+                    "profile": profile_name,
+                    "config_file": path.display().to_string(),
+                })),
+            )
+        }
+    }
+}
+
+pub(crate) mod instantiate {
+    use crate::cli::arg;
+    use crate::cli::template_address;
+    use crate::output::CommandOutput;
+    use clap::Args;
+    use std::collections::HashSet;
+    use tari_stable_coin_example::config::ConfigFile;
+    use tari_stable_coin_example::config::Deployment;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
+
+    use tari_engine_types::commit_result::FinalizeResult;
+    use tari_engine_types::commit_result::TransactionResult;
+    use tari_engine_types::instruction::Instruction;
+    use tari_engine_types::substate::SubstateAddress;
+    use tari_engine_types::substate::SubstateValue;
     use tari_engine_types::TemplateAddress;
+    use tari_template_lib::prelude::ResourceType;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
@@ -124,22 +539,25 @@ pub(crate) mod instantiate {
         pub async fn run(
             self,
             mut client: DaemonClient,
-            template_address: TemplateAddress,
+            deployment: &Deployment,
             dump_buckets: bool,
             fees: u64,
-        ) {
-            // let template_address= ;
+            config: &mut ConfigFile,
+            profile_name: &str,
+        ) -> Result<CommandOutput, ClientError> {
+            let template = deployment.template()?;
+            let template_address = template_address(template)?;
             let function = "instantiate".to_string();
 
-            client
+            let outcome = client
                 .submit_instruction(
                     Instruction::CallFunction {
                         template_address,
                         function,
                         args: vec![
-                            parse_arg(&self.initial_token_supply).unwrap(),
-                            parse_arg(&self.token_symbol).unwrap(),
-                            parse_arg(&self.token_metadata).unwrap(),
+                            arg("initial_token_supply", &self.initial_token_supply)?,
+                            arg("token_symbol", &self.token_symbol)?,
+                            arg("token_metadata", &self.token_metadata)?,
                         ],
                     },
                     dump_buckets,
@@ -147,712 +565,1876 @@ pub(crate) mod instantiate {
                     fees,
                     vec![],
                 )
-                .await;
-            println!("done");
+                .await?;
+
+            // Without a finalized result (e.g. with --no-wait) there is nothing to capture
+            let result = match &outcome.result {
+                Some(result) if outcome.reject_reason().is_none() => result,
+                _ => return Ok(outcome.into()),
+            };
+            let mut created = deployment_from_result(template_address, result)?;
+            created.template = Some(template.to_string());
+            let path = config.save_deployment(profile_name, &created)?;
+
+            Ok(
+                CommandOutput::from(outcome).with_return_values(serde_json::json!({
+                    "coin_component": created.coin_component,
+                    "admin_badge_resource": created.admin_badge_resource,
+                    "user_badge_resource": created.user_badge_resource,
+                    "coin_resource": created.coin_resource,
+                    "profile": profile_name,
+                    "config_file": path.display().to_string(),
+                })),
+            )
         }
     }
+
+    /// Finds the addresses created by the template's `instantiate` function in the substate diff
+    /// of its transaction.
+    ///
+    /// The coin is the only fungible resource created. Of the two non-fungible resources, the
+    /// admin badge is the one a token was minted for, since the first admin badge is handed to
+    /// the caller; user badges are only minted later by `create_new_user`.
+    pub fn deployment_from_result(
+        template_address: TemplateAddress,
+        result: &FinalizeResult,
+    ) -> Result<Deployment, ClientError> {
+        let diff = match &result.result {
+            TransactionResult::Accept(diff) => diff,
+            _ => {
+                return Err(ClientError::InvalidResponse(
+                    "the instantiate transaction was not accepted".to_string(),
+                ))
+            }
+        };
+
+        let mut components = vec![];
+        let mut fungible = vec![];
+        let mut non_fungible = vec![];
+        let mut minted = HashSet::new();
+        for (address, substate) in diff.up_iter() {
+            match (address, substate.substate_value()) {
+                (SubstateAddress::Component(address), SubstateValue::Component(header))
+                    if header.template_address == template_address =>
+                {
+                    components.push(*address);
+                }
+                (SubstateAddress::Resource(address), SubstateValue::Resource(resource)) => {
+                    match resource.resource_type() {
+                        ResourceType::Fungible => fungible.push(*address),
+                        ResourceType::NonFungible => non_fungible.push(*address),
+                        _ => {}
+                    }
+                }
+                (SubstateAddress::NonFungible(address), _) => {
+                    minted.insert(*address.resource_address());
+                }
+                _ => {}
+            }
+        }
+
+        let unexpected = |what: &str| {
+            ClientError::InvalidResponse(format!(
+                "could not identify the {} in the instantiate result",
+                what
+            ))
+        };
+        let [coin_component] = &components[..] else {
+            return Err(unexpected("coin component"));
+        };
+        let [coin_resource] = &fungible[..] else {
+            return Err(unexpected("coin resource"));
+        };
+        let (admin_badges, user_badges): (Vec<_>, Vec<_>) =
+            non_fungible.into_iter().partition(|r| minted.contains(r));
+        let ([admin_badge_resource], [user_badge_resource]) = (&admin_badges[..], &user_badges[..])
+        else {
+            return Err(unexpected("admin and user badge resources"));
+        };
+
+        Ok(Deployment {
+            template: None,
+            coin_component: Some(coin_component.to_string()),
+            admin_badge_resource: Some(admin_badge_resource.to_string()),
+            user_badge_resource: Some(user_badge_resource.to_string()),
+            coin_resource: Some(coin_resource.to_string()),
+        })
+    }
 }
 
 pub(crate) mod increase_supply {
-    use crate::daemon_client::DaemonClient;
-    use crate::Cli;
     use clap::Args;
-
-    use tari_template_lib::args;
-
-    use tari_template_lib::prelude::ComponentAddress;
-
-    use tari_transaction::Transaction;
-
-    use std::str::FromStr;
-    use tari_template_lib::prelude::ResourceAddress;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub account_component_address: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub account_component_address: AccountArg,
         pub amount: u64,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-            cli: Cli,
-        ) {
-            // let template_address= ;
-
-            let instructions = Transaction::builder()
-                .create_proof(
-                    ComponentAddress::from_str(&self.account_component_address).unwrap(),
-                    ResourceAddress::from_str(&cli.admin_badge_resource).unwrap(),
-                )
-                .put_last_instruction_output_on_workspace("proof")
-                .call_method(
-                    ComponentAddress::from_str(&cli.default_coin_component).unwrap(),
-                    "increase_supply",
-                    args![self.amount],
-                )
-                .drop_all_proofs_in_workspace()
-                .build_as_instructions();
-
-            // .
-            // let mut instructions = vec![];
-
-            // instructions.push(Instruction::CallMethod {
-            // component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-            // method,
-            // args: args![parse_arg(&self.amount).unwrap(),],
-            // });
-
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![cli.default_coin_component.parse().unwrap()],
-                )
-                .await;
-            println!("done");
+                .mint(self.account_component_address.address()?, self.amount)
+                .await
         }
     }
 }
 
 pub(crate) mod decrease_supply {
-    use crate::daemon_client::DaemonClient;
     use clap::Args;
-
-    use tari_engine_types::instruction::Instruction;
-    use tari_engine_types::parse_arg;
-
-    use tari_template_lib::args;
+    use tari_stable_coin_example::address::parse_component_address;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     use tari_template_lib::prelude::ComponentAddress;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub component_address: String,
+        #[clap(value_parser = parse_component_address)]
+        pub component_address: ComponentAddress,
 
-        pub amount: String,
+        pub amount: u64,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-        ) {
-            // let template_address= ;
-            let method = "decrease_supply".to_string();
-
-            let mut instructions = vec![];
-
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-                method,
-                args: args![parse_arg(&self.amount).unwrap(),],
-            });
-
+            client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
-                )
-                .await;
-            println!("done");
+                .with_coin_component(self.component_address)
+                .burn(self.amount)
+                .await
         }
     }
 }
 
 pub(crate) mod total_supply {
-    use crate::daemon_client::DaemonClient;
+    use crate::output::CommandOutput;
     use clap::Args;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
 
-    use tari_engine_types::instruction::Instruction;
-
-    use tari_template_lib::args;
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {}
 
-    use tari_template_lib::prelude::ComponentAddress;
+    impl Command {
+        pub async fn run(self, mut client: StablecoinClient) -> Result<CommandOutput, ClientError> {
+            let total_supply = client.total_supply().await?;
+            Ok(CommandOutput::default()
+                .with_return_values(serde_json::json!({ "total_supply": total_supply.value() })))
+        }
+    }
+}
 
-    use crate::Cli;
-    use std::str::FromStr;
+pub(crate) mod balance {
+    use crate::output::CommandOutput;
+    use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::amount::format_amount;
+    use tari_stable_coin_example::client::resource_address;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::daemon_client::VaultBalance;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
 
     #[derive(Debug, Args, Clone)]
-    pub struct Command {}
+    pub struct Command {
+        /// Account address, alias or wallet account name. Defaults to the profile's account
+        #[clap(value_parser = AccountArg::parse)]
+        pub account: Option<AccountArg>,
+        /// Also list the other resources held by the account, such as badges
+        #[clap(long)]
+        pub all: bool,
+    }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-            cli: Cli,
-        ) {
-            // let template_address= ;
-            let method = "total_supply".to_string();
-
-            let mut instructions = vec![];
-
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_str(&cli.default_coin_component).unwrap(),
-                method,
-                args: args![],
+            client: DaemonClient,
+            profile: &Profile,
+        ) -> Result<CommandOutput, ClientError> {
+            let deployment = &profile.deployment;
+            let account = self.account.as_ref().map(AccountArg::address).transpose()?;
+            let balances = client.account_balances(account).await?;
+
+            let coin_resource = resource_address(deployment.coin_resource()?)?;
+            let (coin, others): (Vec<_>, Vec<_>) = balances
+                .iter()
+                .partition(|entry| entry.resource == coin_resource);
+            let balance: i64 = coin.iter().map(|entry| entry.balance.value()).sum();
+
+            let mut values = serde_json::json!({
+                "account": account
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|| profile.account()),
+                "balance": format_amount(balance, profile.decimals()),
+                "raw_balance": balance,
+                "vaults": coin.iter().map(|entry| entry.vault.to_string()).collect::<Vec<_>>(),
             });
 
-            client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![cli.default_coin_component.parse().unwrap()],
-                )
-                .await;
-            println!("done");
+            if self.all {
+                let names = [
+                    (deployment.admin_badge_resource.as_deref(), "admin_badge"),
+                    (deployment.user_badge_resource.as_deref(), "user_badge"),
+                ];
+                let name_of = |entry: &VaultBalance| {
+                    names.iter().find_map(|(address, name)| {
+                        let address = resource_address((*address)?).ok()?;
+                        (address == entry.resource).then_some(*name)
+                    })
+                };
+                values["other_resources"] = others
+                    .into_iter()
+                    .map(|entry| {
+                        serde_json::json!({
+                            "resource": entry.resource.to_string(),
+                            "name": name_of(entry),
+                            "balance": entry.balance.value(),
+                            "vault": entry.vault.to_string(),
+                        })
+                    })
+                    .collect();
+            }
+
+            Ok(CommandOutput::default().with_return_values(values))
         }
     }
 }
 
-pub(crate) mod withdraw {
-    use crate::daemon_client::DaemonClient;
+pub(crate) mod history {
+    use crate::output::CommandOutput;
+    use chrono::NaiveDate;
     use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::amount::format_amount;
+    use tari_stable_coin_example::client::component_address;
+    use tari_stable_coin_example::client::resource_address;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::history::coin_movements;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
 
-    use tari_template_lib::args;
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        /// Only list transactions that move coins into or out of this account. Directions are
+        /// given from this account's point of view, or the profile account's if none is given
+        #[clap(long, value_parser = AccountArg::parse)]
+        pub account: Option<AccountArg>,
+        /// Only list transactions from this day on (YYYY-MM-DD)
+        #[clap(long, value_parser = parse_date)]
+        pub since: Option<NaiveDate>,
+        /// Only list transactions up to and including this day (YYYY-MM-DD)
+        #[clap(long, value_parser = parse_date)]
+        pub until: Option<NaiveDate>,
+        /// Only list transactions with this status, e.g. Accepted or Rejected
+        #[clap(long)]
+        pub status: Option<String>,
+    }
 
-    use tari_template_lib::prelude::ComponentAddress;
+    fn parse_date(value: &str) -> Result<NaiveDate, String> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|e| format!("expected a date like 2023-09-30: {}", e))
+    }
 
-    use crate::Cli;
-    use std::str::FromStr;
-    use tari_template_lib::prelude::ResourceAddress;
-    use tari_transaction::Transaction;
+    impl Command {
+        pub async fn run(
+            self,
+            client: DaemonClient,
+            profile: &Profile,
+        ) -> Result<CommandOutput, ClientError> {
+            let deployment = &profile.deployment;
+            let coin_component = component_address(deployment.coin_component()?)?;
+            let coin_resource = resource_address(deployment.coin_resource()?)?;
+            let filter_account = self.account.as_ref().map(AccountArg::address).transpose()?;
+            let account = match filter_account {
+                Some(address) => address,
+                None => client.account_address(&profile.account()).await?,
+            };
+
+            let mut transactions = client.transactions().await?;
+            transactions.sort_by_key(|tx| tx.time);
+
+            let mut rows = vec![];
+            for tx in transactions {
+                let date = tx.time.date();
+                if self.since.map_or(false, |since| date < since)
+                    || self.until.map_or(false, |until| date > until)
+                {
+                    continue;
+                }
+                let status = format!("{:?}", tx.status);
+                if let Some(wanted) = &self.status {
+                    if !status.eq_ignore_ascii_case(wanted) {
+                        continue;
+                    }
+                }
+                let fee = tx
+                    .result
+                    .as_ref()
+                    .map(|result| result.fee_receipt.total_fees_paid().value());
+
+                let movements = coin_movements(
+                    tx.transaction.instructions(),
+                    &coin_component,
+                    &coin_resource,
+                );
+                for movement in movements {
+                    if filter_account.map_or(false, |address| !movement.involves(&address)) {
+                        continue;
+                    }
+                    let (direction, counterparty) =
+                        match (movement.from == Some(account), movement.to == Some(account)) {
+                            (true, true) => ("self", None),
+                            (true, false) => ("out", movement.to),
+                            (false, true) => ("in", movement.from),
+                            (false, false) => ("", None),
+                        };
+                    rows.push(serde_json::json!({
+                        "time": tx.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        "transaction_id": tx.transaction.id().to_string(),
+                        "kind": movement.kind.to_string(),
+                        "direction": direction,
+                        "counterparty": counterparty.map(|address| address.to_string()),
+                        "amount": movement
+                            .amount
+                            .map(|amount| format_amount(amount as i64, profile.decimals())),
+                        "fee": fee,
+                        "status": status,
+                    }));
+                }
+            }
+
+            Ok(CommandOutput::default()
+                .with_return_values(serde_json::json!({ "transactions": rows })))
+        }
+    }
+}
+
+pub(crate) mod withdraw {
+    use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub admin_account_component: String,
-        pub into_account: String,
-        pub amount: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub admin_account_component: AccountArg,
+        #[clap(value_parser = AccountArg::parse)]
+        pub into_account: AccountArg,
+        pub amount: u64,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-            cli: Cli,
-        ) {
-            // let template_address= ;
-
-            let instructions = Transaction::builder()
-                .create_proof(
-                    ComponentAddress::from_str(&self.admin_account_component).unwrap(),
-                    ResourceAddress::from_str(&cli.admin_badge_resource).unwrap(),
-                )
-                .put_last_instruction_output_on_workspace("proof")
-                .call_method(
-                    ComponentAddress::from_str(&cli.default_coin_component).unwrap(),
-                    "withdraw",
-                    args![self.amount.parse::<u64>().unwrap()],
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
+            client
+                .issue(
+                    self.admin_account_component.address()?,
+                    self.into_account.address()?,
+                    self.amount,
                 )
-                .put_last_instruction_output_on_workspace("bucket")
-                .call_method(
-                    ComponentAddress::from_str(&self.into_account).unwrap(),
-                    "deposit",
-                    args![Variable("bucket"),],
+                .await
+        }
+    }
+}
+
+pub(crate) mod mint_and_distribute {
+    use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
+
+    #[derive(Debug, Clone)]
+    pub struct Distribution {
+        pub account: AccountArg,
+        pub amount: u64,
+    }
+
+    impl Distribution {
+        /// Parses `<account>=<amount>`.
+        fn parse(value: &str) -> Result<Self, String> {
+            let (account, amount) = value.rsplit_once('=').ok_or_else(|| {
+                format!(
+                    "'{}' is not a distribution, expected <account>=<amount>",
+                    value
                 )
-                .drop_all_proofs_in_workspace()
-                .build_as_instructions();
+            })?;
+            let amount = amount
+                .parse()
+                .map_err(|_| format!("'{}' is not an amount of coins", amount))?;
+            Ok(Self {
+                account: AccountArg::parse(account)?,
+                amount,
+            })
+        }
+    }
 
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        #[clap(value_parser = AccountArg::parse)]
+        pub admin_account_component: AccountArg,
+        /// The accounts to pay and how many new coins each gets, as <account>=<amount>
+        #[clap(value_parser = Distribution::parse, required = true)]
+        pub distributions: Vec<Distribution>,
+    }
+
+    impl Command {
+        pub async fn run(
+            self,
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
+            let payments = self
+                .distributions
+                .iter()
+                .map(|d| Ok((d.account.address()?, d.amount)))
+                .collect::<Result<Vec<_>, ClientError>>()?;
             client
-                .submit_instructions(instructions, dump_buckets, is_dry_run, fees, vec![])
-                .await;
-            println!("done");
+                .mint_and_distribute(self.admin_account_component.address()?, &payments)
+                .await
         }
     }
 }
 
 pub(crate) mod deposit {
-    use crate::daemon_client::DaemonClient;
     use clap::Args;
+    use tari_stable_coin_example::address::parse_resource_address;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::client::component_requirement;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
-    use std::str::FromStr;
     use tari_engine_types::instruction::Instruction;
 
     use tari_template_lib::args;
 
-    use tari_template_lib::prelude::ComponentAddress;
     use tari_template_lib::prelude::ResourceAddress;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub component_address: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub component_address: AccountArg,
 
         pub bucket_amount: u64,
-        pub bucket_resource: String,
-        pub bucket_withdraw_from_component: String,
+        #[clap(value_parser = parse_resource_address)]
+        pub bucket_resource: ResourceAddress,
+        #[clap(value_parser = AccountArg::parse)]
+        pub bucket_withdraw_from_component: AccountArg,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-        ) {
-            // let template_address= ;
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             let method = "deposit".to_string();
+            let component_address = self.component_address.address()?;
 
             let mut instructions = vec![];
 
             instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.bucket_withdraw_from_component)
-                    .unwrap(),
+                component_address: self.bucket_withdraw_from_component.address()?,
                 method: "withdraw".to_string(),
-                args: args![
-                    ResourceAddress::from_str(&self.bucket_resource).unwrap(),
-                    self.bucket_amount
-                ],
+                args: args![self.bucket_resource, self.bucket_amount],
             });
             instructions.push(Instruction::PutLastInstructionOutputOnWorkspace {
                 key: b"bucket_bucket".to_vec(),
             });
 
             instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
+                component_address,
                 method,
                 args: args![Variable("bucket_bucket"),],
             });
 
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
-                )
-                .await;
-            println!("done");
+                .submit_instructions(instructions, vec![component_requirement(component_address)])
+                .await
         }
     }
 }
 
 pub(crate) mod send {
-    use crate::daemon_client::DaemonClient;
     use clap::Args;
-
-    use tari_template_lib::args;
-
-    use tari_template_lib::prelude::ComponentAddress;
-
-    use crate::Cli;
-    use std::str::FromStr;
-    use tari_template_lib::prelude::ResourceAddress;
-    use tari_transaction::Transaction;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub from_component: String,
-        pub to_component: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub from_component: AccountArg,
+        #[clap(value_parser = AccountArg::parse)]
+        pub to_component: AccountArg,
         pub amount: u64,
     }
 
     impl Command {
-        pub async fn run(self, mut client: DaemonClient, is_dry_run: bool, fees: u64, cli: Cli) {
-            let instructions = Transaction::builder()
-                .create_proof(
-                    ComponentAddress::from_str(&self.from_component).unwrap(),
-                    ResourceAddress::from_str(&cli.user_badge_resource).unwrap(),
-                )
-                .put_last_instruction_output_on_workspace("proof")
-                .call_method(
-                    ComponentAddress::from_str(&self.from_component).unwrap(),
-                    "withdraw",
-                    args![
-                        ResourceAddress::from_str(&cli.coin_resource)
-                            .expect("bad resource address"),
-                        self.amount
-                    ],
-                )
-                .put_last_instruction_output_on_workspace("bucket")
-                .call_method(
-                    ComponentAddress::from_str(&self.to_component).unwrap(),
-                    "deposit",
-                    args![Variable("bucket"),],
-                )
-                .drop_all_proofs_in_workspace()
-                .build_as_instructions();
-
+        pub async fn run(
+            self,
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    false,
-                    is_dry_run,
-                    fees,
-                    vec![cli.default_coin_component.parse().unwrap()],
+                .transfer(
+                    self.from_component.address()?,
+                    self.to_component.address()?,
+                    self.amount,
                 )
-                .await;
-            println!("done");
+                .await
         }
     }
 }
 
-pub(crate) mod create_new_admin {
-    use crate::daemon_client::DaemonClient;
+pub(crate) mod batch_send {
+    use crate::cli::resolve_account;
+    use crate::output::CommandOutput;
     use clap::Args;
-
-    use tari_engine_types::instruction::Instruction;
-
-    use tari_template_lib::args;
-
-    use tari_template_lib::prelude::ComponentAddress;
+    use std::collections::BTreeMap;
+    use std::collections::BTreeSet;
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::address::parse_transaction_id;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::batch::read_rows;
+    use tari_stable_coin_example::batch::BatchState;
+    use tari_stable_coin_example::batch::PaymentStatus;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub component_address: String,
+        /// The paying account, which must hold a user badge
+        #[clap(value_parser = AccountArg::parse)]
+        pub from_component: AccountArg,
+        /// CSV file with a `recipient,amount` header. Recipients are account addresses, aliases
+        /// or wallet account names
+        pub csv: PathBuf,
+        /// File recording which rows have been paid [default: <csv>.state.json]
+        #[clap(long, value_name = "FILE")]
+        pub state: Option<PathBuf>,
+        /// The most payments to put in one transaction. Fewer are sent together if a dry run
+        /// shows the transaction would be rejected or cost more than the maximum fee
+        #[clap(long, default_value = "10")]
+        pub max_rows: NonZeroUsize,
+        /// Send rows again whose last attempt stopped before it was known whether their
+        /// transaction reached the daemon. Check the account's history first
+        #[clap(long)]
+        pub retry_unknown: bool,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-        ) {
-            // let template_address= ;
-            let method = "create_new_admin".to_string();
+            mut client: StablecoinClient,
+            aliases: &BTreeMap<String, String>,
+            dry_run: bool,
+        ) -> Result<CommandOutput, ClientError> {
+            if client.daemon().is_preparing() {
+                return Err(ClientError::invalid_argument(
+                    "prepare",
+                    "batch-send submits several transactions, which cannot be prepared",
+                ));
+            }
+            let from = self.from_component.address()?;
+            let rows = read_rows(&self.csv)?;
+            let state_path = self
+                .state
+                .clone()
+                .unwrap_or_else(|| BatchState::default_path(&self.csv));
+            let mut state = match BatchState::load(&state_path)? {
+                Some(state) => {
+                    state.check_matches(&from.to_string(), &rows)?;
+                    state
+                }
+                None => BatchState::new(from.to_string(), rows),
+            };
+
+            // Resolve every recipient before paying anyone, so a typo stops the whole batch
+            let mut recipients = Vec::with_capacity(state.payments.len());
+            for payment in &state.payments {
+                let recipient = AccountArg::parse(&payment.row.recipient)
+                    .map_err(|reason| ClientError::invalid_argument("recipient", reason))?;
+                recipients.push(resolve_account(&recipient, aliases, client.daemon()).await?);
+            }
+
+            // Transactions submitted by an earlier run may have finalized since
+            let submitted: BTreeSet<String> = state
+                .payments
+                .iter()
+                .filter(|p| p.status == PaymentStatus::Submitted)
+                .filter_map(|p| p.transaction_id.clone())
+                .collect();
+            for transaction_id in submitted {
+                let id = parse_transaction_id(&transaction_id)
+                    .map_err(|reason| ClientError::invalid_argument("state", reason))?;
+                let outcome = client.daemon().transaction_outcome(id).await?;
+                let (status, error) = settled(&outcome);
+                for payment in &mut state.payments {
+                    if payment.transaction_id.as_deref() == Some(transaction_id.as_str()) {
+                        payment.status = status;
+                        payment.error = error.clone();
+                    }
+                }
+            }
+            if !dry_run {
+                state.save(&state_path)?;
+            }
+
+            let to_send: Vec<usize> = state
+                .payments
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| match p.status {
+                    PaymentStatus::Pending | PaymentStatus::Failed => true,
+                    PaymentStatus::Submitting => self.retry_unknown,
+                    PaymentStatus::Submitted | PaymentStatus::Paid => false,
+                })
+                .map(|(i, _)| i)
+                .collect();
+
+            let mut estimated_fee = 0;
+            let mut chunk_size = self.max_rows.get();
+            let mut start = 0;
+            while start < to_send.len() {
+                let chunk = &to_send[start..(start + chunk_size).min(to_send.len())];
+                let payments: Vec<_> = chunk
+                    .iter()
+                    .map(|&i| (recipients[i], state.payments[i].row.amount))
+                    .collect();
+
+                match client.batch_transfer_fee(from, &payments).await {
+                    Ok(fee) => estimated_fee += fee.value(),
+                    // Try fewer payments at a time until only the row that fails is left
+                    Err(ClientError::TransactionRejected(_)) if chunk.len() > 1 => {
+                        chunk_size = chunk.len() / 2;
+                        continue;
+                    }
+                    Err(ClientError::TransactionRejected(reason)) => {
+                        let payment = &mut state.payments[chunk[0]];
+                        payment.status = PaymentStatus::Failed;
+                        payment.error = Some(reason);
+                        if !dry_run {
+                            state.save(&state_path)?;
+                        }
+                        chunk_size = self.max_rows.get();
+                        start += 1;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+                if dry_run {
+                    start += chunk.len();
+                    continue;
+                }
+
+                for &i in chunk {
+                    let payment = &mut state.payments[i];
+                    payment.status = PaymentStatus::Submitting;
+                    payment.transaction_id = None;
+                    payment.error = None;
+                }
+                state.save(&state_path)?;
+                // On an error it is unknown whether the transaction reached the daemon, so the
+                // rows are left as submitting
+                let outcome = client.batch_transfer(from, &payments).await?;
+                for &i in chunk {
+                    let payment = &mut state.payments[i];
+                    payment.status = PaymentStatus::Submitted;
+                    payment.transaction_id = outcome.transaction_id_hex();
+                }
+                state.save(&state_path)?;
+
+                // The rows now have a transaction id, so if waiting fails the next run finds
+                // out how it ended
+                let waiting = client.daemon().waits_for_finalization();
+                if let Some(id) = outcome.transaction_id.filter(|_| waiting) {
+                    let outcome = client.daemon().transaction_outcome(id).await?;
+                    let (status, error) = settled(&outcome);
+                    for &i in chunk {
+                        let payment = &mut state.payments[i];
+                        payment.status = status;
+                        payment.error = error.clone();
+                    }
+                    state.save(&state_path)?;
+                }
+                start += chunk.len();
+            }
+
+            let count = |status| state.payments.iter().filter(|p| p.status == status).count();
+            let mut values = serde_json::json!({
+                "state_file": state_path.display().to_string(),
+                "paid": count(PaymentStatus::Paid),
+                "submitted": count(PaymentStatus::Submitted),
+                "unknown": count(PaymentStatus::Submitting),
+                "failed": count(PaymentStatus::Failed),
+                "rows": state.payments,
+            });
+            if dry_run {
+                values["estimated_fee"] = estimated_fee.into();
+            }
+            let output = CommandOutput::default().with_return_values(values);
+            let failed = count(PaymentStatus::Failed);
+            Ok(match failed {
+                0 => output,
+                failed => output.with_error(ClientError::TransactionRejected(format!(
+                    "{} of {} payments failed",
+                    failed,
+                    state.payments.len()
+                ))),
+            })
+        }
+    }
 
-            let mut instructions = vec![];
+    /// What a finished attempt means for the rows it paid.
+    fn settled(outcome: &TransactionOutcome) -> (PaymentStatus, Option<String>) {
+        match outcome.reject_reason() {
+            Some(reason) => (PaymentStatus::Failed, Some(reason)),
+            None if outcome.timed_out || outcome.status.is_none() => {
+                (PaymentStatus::Submitted, None)
+            }
+            None => (PaymentStatus::Paid, None),
+        }
+    }
+}
 
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-                method,
-                args: args![],
-            });
+pub(crate) mod create_new_admin {
+    use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        /// The account holding the admin badge that authorizes the new one
+        #[clap(value_parser = AccountArg::parse)]
+        pub admin_account_component: AccountArg,
+        /// The account to send the new admin badge to
+        #[clap(value_parser = AccountArg::parse)]
+        pub new_admin_component: AccountArg,
+    }
+
+    impl Command {
+        pub async fn run(
+            self,
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
+                .create_admin(
+                    self.admin_account_component.address()?,
+                    self.new_admin_component.address()?,
                 )
-                .await;
-            println!("done");
+                .await
         }
     }
 }
 
 pub(crate) mod create_new_user {
-    use crate::daemon_client::DaemonClient;
     use clap::Args;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
-    use tari_template_lib::args;
-
-    use tari_template_lib::prelude::ComponentAddress;
-
-    use crate::Cli;
-    use std::str::FromStr;
-    use tari_template_lib::prelude::ResourceAddress;
-    use tari_transaction::Transaction;
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub admin_account_component: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub admin_account_component: AccountArg,
         pub user_id: u64,
-        pub send_to_user_component: String,
+        #[clap(value_parser = AccountArg::parse)]
+        pub send_to_user_component: AccountArg,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-            cli: Cli,
-        ) {
-            // let template_address= ;
-
-            let instructions = Transaction::builder()
-                .create_proof(
-                    ComponentAddress::from_str(&self.admin_account_component).unwrap(),
-                    ResourceAddress::from_str(&cli.admin_badge_resource).unwrap(),
-                )
-                .put_last_instruction_output_on_workspace("proof")
-                .call_method(
-                    ComponentAddress::from_str(&cli.default_coin_component).unwrap(),
-                    "create_new_user",
-                    args![self.user_id],
-                )
-                .put_last_instruction_output_on_workspace("bucket")
-                .call_method(
-                    ComponentAddress::from_str(&self.send_to_user_component).unwrap(),
-                    "deposit",
-                    args![Variable("bucket"),],
-                )
-                .drop_all_proofs_in_workspace()
-                .build_as_instructions();
-
+            mut client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![cli.default_coin_component.parse().unwrap()],
+                .create_user(
+                    self.admin_account_component.address()?,
+                    self.user_id,
+                    self.send_to_user_component.address()?,
                 )
-                .await;
-            println!("done");
+                .await
         }
     }
 }
 
 pub(crate) mod blacklist_user {
-    use crate::daemon_client::DaemonClient;
+    use crate::output::CommandOutput;
+    use chrono::Utc;
     use clap::Args;
+    use tari_stable_coin_example::address::parse_vault_id;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::journal::Journal;
+    use tari_stable_coin_example::journal::JournalAction;
+    use tari_stable_coin_example::journal::JournalEntry;
+    use tari_stable_coin_example::users::account_badge;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::IndexerClient;
+    use tari_stable_coin_example::StablecoinClient;
 
-    use tari_template_lib::args;
-
-    use tari_template_lib::models::NonFungibleAddress;
-    use tari_template_lib::prelude::ComponentAddress;
-    use tari_transaction::SubstateRequirement;
-
-    use crate::Cli;
-    use std::str::FromStr;
-    use tari_engine_types::parse_arg;
-    use tari_engine_types::substate::SubstateAddress;
-    use tari_template_lib::prelude::NonFungibleId;
-    use tari_template_lib::prelude::ResourceAddress;
     use tari_template_lib::prelude::VaultId;
-    use tari_transaction::Transaction;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub admin_account_component: String,
-        pub from_vault: String,
-        pub user_id: u64,
+        #[clap(value_parser = AccountArg::parse)]
+        pub admin_account_component: AccountArg,
+        /// The vault holding the user's badge. Not needed with --account
+        #[clap(
+            value_parser = parse_vault_id,
+            requires = "user_id",
+            required_unless_present = "account"
+        )]
+        pub from_vault: Option<VaultId>,
+        pub user_id: Option<u64>,
+        /// Blacklist the user whose badge this account holds, looking up the badge and its
+        /// vault through the indexer
+        #[clap(
+            long,
+            value_parser = AccountArg::parse,
+            conflicts_with_all = ["from_vault", "user_id"]
+        )]
+        pub account: Option<AccountArg>,
     }
 
     impl Command {
-        pub async fn run(self, mut client: DaemonClient, is_dry_run: bool, fees: u64, cli: Cli) {
-            // let template_address= ;
-
-            let instructions = Transaction::builder()
-                .create_proof(
-                    ComponentAddress::from_str(&self.admin_account_component).unwrap(),
-                    ResourceAddress::from_str(&cli.admin_badge_resource).unwrap(),
-                )
-                .put_last_instruction_output_on_workspace("proof")
-                .call_method(
-                    ComponentAddress::from_str(&cli.default_coin_component).unwrap(),
-                    "blacklist_user",
-                    args![VaultId::from_hex(&self.from_vault).unwrap(), self.user_id],
-                )
-                .drop_all_proofs_in_workspace()
-                .build_as_instructions();
-
-            client
-                .submit_instructions(
-                    instructions,
-                    false,
-                    is_dry_run,
-                    fees,
-                    vec![
-                        format!("vault_{}", self.from_vault).parse().unwrap(),
-                        SubstateRequirement::new(
-                            SubstateAddress::NonFungible(NonFungibleAddress::new(
-                                ResourceAddress::from_str(&cli.user_badge_resource).unwrap(),
-                                NonFungibleId::from_u64(self.user_id),
-                            )),
-                            None,
-                        ),
-                    ],
-                )
-                .await;
-            println!("done");
+        pub async fn run(
+            self,
+            mut client: StablecoinClient,
+            indexer: IndexerClient,
+            journal: &Journal,
+        ) -> Result<CommandOutput, ClientError> {
+            let admin_account = self.admin_account_component.address()?;
+            let (from_vault, user_id, looked_up) =
+                match (&self.account, self.from_vault, self.user_id) {
+                    (Some(account), _, _) => {
+                        let account = account.address()?;
+                        let badge = account_badge(&indexer, client.deployment(), account).await?;
+                        (badge.badge_vault, badge.user_id, Some((account, badge)))
+                    }
+                    (None, Some(from_vault), Some(user_id)) => (from_vault, user_id, None),
+                    _ => unreachable!("clap requires an account or a vault and user id"),
+                };
+
+            let outcome = client.blacklist(admin_account, from_vault, user_id).await?;
+            if outcome.is_submitted() {
+                journal.append(&JournalEntry {
+                    time: Utc::now(),
+                    action: JournalAction::Blacklist,
+                    user_id,
+                    vault: Some(from_vault.to_string()),
+                    account: looked_up.map(|(account, _)| account.to_string()),
+                    admin_account: Some(admin_account.to_string()),
+                    wallet_account: client.daemon().default_account().to_string(),
+                    transaction_id: outcome.transaction_id_hex(),
+                })?;
+            }
+
+            let output = CommandOutput::from(outcome);
+            Ok(match looked_up {
+                Some((account, badge)) => output.with_return_values(serde_json::json!({
+                    "account": account.to_string(),
+                    "user_id": badge.user_id,
+                    "badge_vault": badge.badge_vault.to_string(),
+                    "coin_vault": badge.coin_vault.map(|vault| vault.to_string()),
+                })),
+                None => output,
+            })
         }
     }
 }
 
 pub(crate) mod remove_from_blacklist {
-    use crate::daemon_client::DaemonClient;
+    use chrono::Utc;
     use clap::Args;
-
-    use tari_engine_types::instruction::Instruction;
-    use tari_engine_types::parse_arg;
-
-    use tari_template_lib::args;
+    use tari_stable_coin_example::address::parse_component_address;
+    use tari_stable_coin_example::journal::Journal;
+    use tari_stable_coin_example::journal::JournalAction;
+    use tari_stable_coin_example::journal::JournalEntry;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
 
     use tari_template_lib::prelude::ComponentAddress;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub component_address: String,
+        #[clap(value_parser = parse_component_address)]
+        pub component_address: ComponentAddress,
 
-        pub user_id: String,
+        pub user_id: u64,
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-        ) {
-            // let template_address= ;
-            let method = "remove_from_blacklist".to_string();
+            client: StablecoinClient,
+            journal: &Journal,
+        ) -> Result<TransactionOutcome, ClientError> {
+            let wallet_account = client.daemon().default_account().to_string();
+            let outcome = client
+                .with_coin_component(self.component_address)
+                .remove_from_blacklist(self.user_id)
+                .await?;
+            if outcome.is_submitted() {
+                journal.append(&JournalEntry {
+                    time: Utc::now(),
+                    action: JournalAction::RemoveFromBlacklist,
+                    user_id: self.user_id,
+                    vault: None,
+                    account: None,
+                    admin_account: None,
+                    wallet_account,
+                    transaction_id: outcome.transaction_id_hex(),
+                })?;
+            }
+            Ok(outcome)
+        }
+    }
+}
 
-            let mut instructions = vec![];
+pub(crate) mod get_user_data {
+    use crate::output::CommandOutput;
+    use clap::Args;
+    use tari_stable_coin_example::address::parse_component_address;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
 
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-                method,
-                args: args![parse_arg(&self.user_id).unwrap(),],
-            });
+    use tari_template_lib::prelude::ComponentAddress;
+
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        #[clap(value_parser = parse_component_address)]
+        pub component_address: ComponentAddress,
+
+        pub user_id: u64,
+    }
+
+    impl Command {
+        pub async fn run(self, client: StablecoinClient) -> Result<CommandOutput, ClientError> {
+            let user_data = client
+                .with_coin_component(self.component_address)
+                .get_user_data(self.user_id)
+                .await?;
+            Ok(CommandOutput::default().with_return_values(serde_json::json!(user_data)))
+        }
+    }
+}
+
+pub(crate) mod set_user_data {
+    use clap::Args;
+    use tari_stable_coin_example::address::parse_component_address;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
+
+    use tari_template_lib::prelude::ComponentAddress;
+
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        #[clap(value_parser = parse_component_address)]
+        pub component_address: ComponentAddress,
+
+        pub user_id: u64,
+
+        pub data: String,
+    }
 
+    impl Command {
+        pub async fn run(
+            self,
+            client: StablecoinClient,
+        ) -> Result<TransactionOutcome, ClientError> {
             client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
-                )
-                .await;
-            println!("done");
+                .with_coin_component(self.component_address)
+                .set_user_data(self.user_id, self.data)
+                .await
         }
     }
 }
 
-pub(crate) mod get_user_data {
-    use crate::daemon_client::DaemonClient;
+pub(crate) mod submit_prepared {
+    use crate::output::CommandOutput;
     use clap::Args;
+    use std::io;
+    use std::io::IsTerminal;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::prepared::PreparedTransaction;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
 
-    use tari_engine_types::instruction::Instruction;
-    use tari_engine_types::parse_arg;
+    #[derive(Debug, Args, Clone)]
+    pub struct Command {
+        /// A transaction file written with --prepare
+        pub file: PathBuf,
+        /// Submit without asking for confirmation after the summary
+        #[clap(long, short = 'y')]
+        pub yes: bool,
+    }
 
-    use tari_template_lib::args;
+    impl Command {
+        pub async fn run(
+            self,
+            mut client: DaemonClient,
+            profile: &Profile,
+            is_dry_run: bool,
+        ) -> Result<CommandOutput, ClientError> {
+            let prepared = PreparedTransaction::load(&self.file)?;
+            let summary = prepared.summary(profile);
+
+            // The review goes to stderr so that it comes before the submission in any output format
+            for line in &summary {
+                eprintln!("{}", line);
+            }
+            let validation = prepared.validate(profile);
+            match &validation {
+                Ok(()) => eprintln!("Checks against the profile passed"),
+                Err(err) => eprintln!("Checks against the profile failed: {}", err),
+            }
+            validation?;
+            // A dry run commits nothing, so there is nothing to confirm
+            if !(self.yes || is_dry_run || confirm()?) {
+                return Err(ClientError::invalid_argument(
+                    "yes",
+                    "the transaction was not submitted",
+                ));
+            }
+
+            let mut request = prepared.request;
+            request.is_dry_run |= is_dry_run;
+            let outcome = client.submit_request(request).await?;
+
+            Ok(CommandOutput::from(outcome)
+                .with_return_values(serde_json::json!({ "summary": summary })))
+        }
+    }
 
+    /// Asks on the terminal whether to submit. Without a terminal to ask on, --yes is required.
+    fn confirm() -> Result<bool, ClientError> {
+        if !io::stdin().is_terminal() {
+            return Err(ClientError::invalid_argument(
+                "yes",
+                "there is no terminal to confirm the submission on, pass --yes",
+            ));
+        }
+        eprint!("Submit this transaction? [y/N] ");
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+pub(crate) mod bootstrap {
+    use crate::cli::instantiate;
+    use crate::cli::resolve_account;
+    use crate::output::CommandOutput;
+    use clap::Args;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::config::ConfigFile;
+    use tari_stable_coin_example::config::Deployment;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_stable_coin_example::TransactionOutcome;
     use tari_template_lib::prelude::ComponentAddress;
 
     #[derive(Debug, Args, Clone)]
     pub struct Command {
-        pub component_address: String,
+        /// TOML file describing the environment to create
+        pub spec_file: PathBuf,
+    }
 
-        pub user_id: String,
+    /// A declarative description of a stablecoin environment. Accounts can be given as an
+    /// address, alias or wallet account name, like on the command line.
+    ///
+    /// ```toml
+    /// symbol = "USDX"
+    /// initial_supply = 1000000
+    /// metadata = "Test coin"
+    /// # The wallet account that pays the fees and receives the first admin badge
+    /// admin_account = "component_..."
+    /// admins = ["component_..."]
+    ///
+    /// [[users]]
+    /// id = 1
+    /// account = "component_..."
+    ///
+    /// [[balances]]
+    /// account = "component_..."
+    /// amount = 1000
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Spec {
+        pub symbol: String,
+        pub initial_supply: u64,
+        pub metadata: String,
+        pub admin_account: String,
+        #[serde(default)]
+        pub admins: Vec<String>,
+        #[serde(default)]
+        pub users: Vec<UserSpec>,
+        #[serde(default)]
+        pub balances: Vec<BalanceSpec>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct UserSpec {
+        pub id: u64,
+        pub account: String,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct BalanceSpec {
+        pub account: String,
+        pub amount: u64,
+    }
+
+    impl Spec {
+        pub fn load(path: &Path) -> Result<Self, ClientError> {
+            let contents = fs::read_to_string(path)?;
+            toml::from_str(&contents).map_err(|e| {
+                ClientError::invalid_argument(
+                    "spec_file",
+                    format!("could not parse {}: {}", path.display(), e),
+                )
+            })
+        }
+    }
+
+    /// The transactions submitted so far.
+    #[derive(Debug, Default)]
+    struct Steps {
+        steps: Vec<serde_json::Value>,
+    }
+
+    impl Steps {
+        fn record(
+            &mut self,
+            step: String,
+            outcome: &TransactionOutcome,
+        ) -> Result<(), ClientError> {
+            self.steps.push(serde_json::json!({
+                "step": step,
+                "transaction_id": outcome.transaction_id_hex(),
+                "status": outcome.status_name(),
+            }));
+            match outcome.reject_reason() {
+                Some(reason) => Err(ClientError::TransactionRejected(format!(
+                    "{} failed: {}",
+                    step, reason
+                ))),
+                // The next step may depend on this one
+                None if outcome.timed_out => {
+                    Err(ClientError::TimedOut(format!("{} did not finalize", step)))
+                }
+                None => Ok(()),
+            }
+        }
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
+            client: DaemonClient,
+            deployment: &Deployment,
             fees: u64,
-        ) {
-            // let template_address= ;
-            let method = "get_user_data".to_string();
+            config: &mut ConfigFile,
+            profile_name: &str,
+        ) -> Result<CommandOutput, ClientError> {
+            let aliases = config.config.aliases.clone();
+            if !client.waits_for_finalization() {
+                return Err(ClientError::invalid_argument(
+                    "--no-wait or --prepare",
+                    "bootstrap needs the result of each step before starting the next",
+                ));
+            }
+            let spec = Spec::load(&self.spec_file)?;
+
+            // Resolve every account before submitting anything, so a bad name stops the bootstrap
+            let admin_account =
+                resolve_spec_account("admin_account", &spec.admin_account, &aliases, &client)
+                    .await?;
+            let mut admins = vec![];
+            for admin in &spec.admins {
+                let address = resolve_spec_account("admins", admin, &aliases, &client).await?;
+                admins.push((admin, address));
+            }
+            let mut users = vec![];
+            for user in &spec.users {
+                let account =
+                    resolve_spec_account("users.account", &user.account, &aliases, &client).await?;
+                users.push((user.id, account));
+            }
+            let mut balances = vec![];
+            for balance in &spec.balances {
+                let account =
+                    resolve_spec_account("balances.account", &balance.account, &aliases, &client)
+                        .await?;
+                balances.push((balance, account));
+            }
+
+            // The first admin badge is dumped into the fee account, which must be the account
+            // that signs the admin steps
+            let fee_account = client.account_address(client.default_account()).await?;
+            if admin_account != fee_account {
+                return Err(ClientError::invalid_argument(
+                    "admin_account",
+                    format!(
+                        "{} is not the fee account {} ({}), which receives the first admin badge",
+                        admin_account,
+                        client.default_account(),
+                        fee_account
+                    ),
+                ));
+            }
+
+            // Steps that finalized are reported even if a later one fails, so it is clear what
+            // exists on the network
+            let mut steps = Steps::default();
+            let mut created = None;
+            let result: Result<(), ClientError> = async {
+                // The first admin badge and any change are dumped into the fee account. The
+                // deployment is saved to the profile as soon as instantiate finalizes
+                let output = instantiate::Command {
+                    initial_token_supply: spec.initial_supply.to_string(),
+                    token_symbol: spec.symbol.clone(),
+                    token_metadata: spec.metadata.clone(),
+                }
+                .run(client.clone(), deployment, true, fees, config, profile_name)
+                .await?;
+                if let Some(outcome) = &output.transaction {
+                    steps.record("instantiate".to_string(), outcome)?;
+                }
+                let deployment = config.profile(Some(profile_name))?.deployment;
+                created = Some(deployment.clone());
+                let mut stablecoin = StablecoinClient::new(client, deployment).with_max_fee(fees);
+
+                for (admin, address) in admins {
+                    let outcome = stablecoin.create_admin(admin_account, address).await?;
+                    steps.record(format!("create_new_admin for {}", admin), &outcome)?;
+                }
+
+                for (id, account) in users {
+                    let outcome = stablecoin.create_user(admin_account, id, account).await?;
+                    steps.record(format!("create_new_user {}", id), &outcome)?;
+                }
+
+                let total: u64 = spec.balances.iter().map(|b| b.amount).sum();
+                if total > spec.initial_supply {
+                    let outcome = stablecoin
+                        .mint(admin_account, total - spec.initial_supply)
+                        .await?;
+                    steps.record("increase_supply".to_string(), &outcome)?;
+                }
+
+                for (balance, account) in balances {
+                    let outcome = stablecoin
+                        .issue(admin_account, account, balance.amount)
+                        .await?;
+                    steps.record(
+                        format!("withdraw {} to {}", balance.amount, balance.account),
+                        &outcome,
+                    )?;
+                }
+                Ok(())
+            }
+            .await;
+
+            let output = CommandOutput::default().with_return_values(serde_json::json!({
+                "profile": profile_name,
+                "deployment": created,
+                "steps": steps.steps,
+            }));
+            Ok(match result {
+                Ok(()) => output,
+                Err(e) => output.with_error(e),
+            })
+        }
+    }
 
-            let mut instructions = vec![];
+    async fn resolve_spec_account(
+        field: &str,
+        value: &str,
+        aliases: &BTreeMap<String, String>,
+        client: &DaemonClient,
+    ) -> Result<ComponentAddress, ClientError> {
+        let account = AccountArg::parse(value)
+            .map_err(|reason| ClientError::invalid_argument(field, reason))?;
+        resolve_account(&account, aliases, client).await
+    }
+}
 
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-                method,
-                args: args![parse_arg(&self.user_id).unwrap(),],
-            });
+pub(crate) mod alias {
+    use crate::output::CommandOutput;
+    use clap::Subcommand;
+    use std::collections::BTreeMap;
+    use tari_stable_coin_example::address::parse_component_address;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::config::ConfigFile;
+    use tari_stable_coin_example::ClientError;
 
-            client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
-                )
-                .await;
-            println!("done");
+    use tari_template_lib::prelude::ComponentAddress;
+
+    /// Manage the local address book of account aliases
+    #[derive(Debug, Subcommand, Clone)]
+    pub enum Command {
+        /// Add or replace an alias for an account component address
+        Add {
+            #[clap(value_parser = parse_alias_name)]
+            name: String,
+            #[clap(value_parser = parse_component_address)]
+            address: ComponentAddress,
+        },
+        /// List all aliases
+        List,
+        /// Remove an alias
+        Remove { name: String },
+    }
+
+    impl Command {
+        pub fn run(self, config: &mut ConfigFile) -> Result<CommandOutput, ClientError> {
+            let aliases = &mut config.config.aliases;
+            match self {
+                Command::Add { name, address } => {
+                    let address = address.to_string();
+                    aliases.insert(name.clone(), address.clone());
+                    config.save()?;
+                    Ok(CommandOutput::default()
+                        .with_return_values(serde_json::json!(BTreeMap::from([(name, address)]))))
+                }
+                Command::List => {
+                    Ok(CommandOutput::default().with_return_values(serde_json::json!(aliases)))
+                }
+                Command::Remove { name } => {
+                    let address = aliases.remove(&name).ok_or_else(|| {
+                        ClientError::invalid_argument("name", format!("no alias '{}'", name))
+                    })?;
+                    config.save()?;
+                    Ok(CommandOutput::default()
+                        .with_return_values(serde_json::json!(BTreeMap::from([(name, address)]))))
+                }
+            }
+        }
+    }
+
+    /// An alias must be something that would be taken as a name where an account is expected.
+    fn parse_alias_name(value: &str) -> Result<String, String> {
+        match AccountArg::parse(value)? {
+            AccountArg::Name(name) => Ok(name),
+            AccountArg::Address(_) => {
+                Err("an alias cannot itself be a component address".to_string())
+            }
         }
     }
 }
 
-pub(crate) mod set_user_data {
-    use crate::daemon_client::DaemonClient;
+pub(crate) mod users {
+    use crate::output::CommandOutput;
     use clap::Args;
+    use clap::Subcommand;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::client::component_address;
+    use tari_stable_coin_example::client::resource_address;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::users::list_users;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
+    use tari_stable_coin_example::IndexerClient;
+
+    /// The users registered with the coin
+    #[derive(Debug, Subcommand, Clone)]
+    pub enum Command {
+        /// List the user badges minted by create-new-user, with who holds them
+        List(ListArgs),
+    }
 
-    use tari_engine_types::instruction::Instruction;
-    use tari_engine_types::parse_arg;
+    #[derive(Debug, Args, Clone)]
+    pub struct ListArgs {
+        /// Skip this many badges, in the order they were minted
+        #[clap(long, default_value = "0")]
+        pub offset: u64,
+        /// List at most this many badges
+        #[clap(long, default_value = "50")]
+        pub limit: u64,
+        /// Also write the list to this file, as CSV if it ends in .csv and JSON otherwise
+        #[clap(long, value_name = "FILE")]
+        pub export: Option<PathBuf>,
+    }
 
-    use tari_template_lib::args;
+    const COLUMNS: &[&str] = &[
+        "user_id",
+        "holder",
+        "account",
+        "vault",
+        "blacklisted",
+        "burnt",
+        "data",
+    ];
 
-    use tari_template_lib::prelude::ComponentAddress;
+    impl Command {
+        pub async fn run(
+            self,
+            client: DaemonClient,
+            indexer: IndexerClient,
+            profile: &Profile,
+            aliases: &BTreeMap<String, String>,
+        ) -> Result<CommandOutput, ClientError> {
+            let Command::List(args) = self;
+            let deployment = &profile.deployment;
+
+            // A badge held outside these accounts is listed without a holder
+            let wallet_accounts = client.accounts().await?;
+            let mut accounts: Vec<_> = wallet_accounts
+                .iter()
+                .map(|(_, address)| *address)
+                .collect();
+            for address in aliases.values() {
+                let address = component_address(address)?;
+                if !accounts.contains(&address) {
+                    accounts.push(address);
+                }
+            }
+
+            let total = indexer
+                .non_fungible_count(resource_address(deployment.user_badge_resource()?)?)
+                .await?;
+            let users =
+                list_users(&indexer, deployment, &accounts, args.offset, args.limit).await?;
+
+            let rows: Vec<_> = users
+                .iter()
+                .map(|user| {
+                    serde_json::json!({
+                        "user_id": super::user_id_value(&user.id),
+                        "holder": user.holder.map(|holder| {
+                            super::account_label(&holder.component, aliases, &wallet_accounts)
+                        }),
+                        "account": user.holder.map(|holder| holder.component.to_string()),
+                        "vault": user.holder.map(|holder| holder.vault.to_string()),
+                        "blacklisted": user.blacklisted,
+                        "burnt": user.burnt,
+                        "data": user.data,
+                    })
+                })
+                .collect();
+
+            if let Some(path) = &args.export {
+                super::export(path, COLUMNS, &rows)?;
+            }
+
+            let next_offset = args.offset + users.len() as u64;
+            let mut values = serde_json::json!({
+                "total": total,
+                "users": rows,
+            });
+            if next_offset < total {
+                values["next_offset"] = next_offset.into();
+            }
+            Ok(CommandOutput::default().with_return_values(values))
+        }
+    }
+}
+
+pub(crate) mod blacklist {
+    use crate::output::CommandOutput;
+    use clap::Args;
+    use clap::Subcommand;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::journal::JournalAction;
+    use tari_stable_coin_example::users::blacklisted_users;
+    use tari_stable_coin_example::users::user_id;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::IndexerClient;
+
+    /// The users whose badges have been recalled by blacklist-user
+    #[derive(Debug, Subcommand, Clone)]
+    pub enum Command {
+        /// List the blacklisted users, with when and by whom they were blacklisted if the local
+        /// journal knows
+        List(ListArgs),
+    }
+
+    #[derive(Debug, Args, Clone)]
+    pub struct ListArgs {
+        /// Also write the list to this file, as CSV if it ends in .csv and JSON otherwise
+        #[clap(long, value_name = "FILE")]
+        pub export: Option<PathBuf>,
+    }
+
+    const COLUMNS: &[&str] = &[
+        "user_id",
+        "vault",
+        "recalled_from",
+        "account",
+        "blacklisted_at",
+        "admin_account",
+        "wallet_account",
+        "transaction_id",
+    ];
+
+    impl Command {
+        pub async fn run(
+            self,
+            indexer: IndexerClient,
+            profile: &Profile,
+        ) -> Result<CommandOutput, ClientError> {
+            let Command::List(args) = self;
+            let blacklisted = blacklisted_users(&indexer, &profile.deployment).await?;
+            let journal = profile.journal().entries()?;
+
+            let rows: Vec<_> = blacklisted
+                .iter()
+                .map(|(id, holder)| {
+                    let user_id = user_id(id);
+                    let entry = journal.iter().rev().find(|entry| {
+                        entry.action == JournalAction::Blacklist && Some(entry.user_id) == user_id
+                    });
+                    serde_json::json!({
+                        "user_id": super::user_id_value(id),
+                        "vault": holder.vault.to_string(),
+                        "recalled_from": entry.and_then(|entry| entry.vault.clone()),
+                        "account": entry.and_then(|entry| entry.account.clone()),
+                        "blacklisted_at": entry.map(|entry| entry.time.to_rfc3339()),
+                        "admin_account": entry.and_then(|entry| entry.admin_account.clone()),
+                        "wallet_account": entry.map(|entry| entry.wallet_account.clone()),
+                        "transaction_id": entry.and_then(|entry| entry.transaction_id.clone()),
+                    })
+                })
+                .collect();
+
+            if let Some(path) = &args.export {
+                super::export(path, COLUMNS, &rows)?;
+            }
+            Ok(CommandOutput::default()
+                .with_return_values(serde_json::json!({ "blacklisted": rows })))
+        }
+    }
+}
+
+pub(crate) mod trace {
+    use crate::output::CommandOutput;
+    use clap::ArgGroup;
+    use clap::Args;
+    use clap::ValueEnum;
+    use std::collections::BTreeMap;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::address::parse_transaction_id;
+    use tari_stable_coin_example::address::AccountArg;
+    use tari_stable_coin_example::amount::format_amount;
+    use tari_stable_coin_example::client::component_address;
+    use tari_stable_coin_example::client::resource_address;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::trace::Direction;
+    use tari_stable_coin_example::trace::Tracer;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::DaemonClient;
+    use tari_stable_coin_example::IndexerClient;
+    use tari_template_lib::prelude::VaultId;
+    use tari_transaction::TransactionId;
 
     #[derive(Debug, Args, Clone)]
+    #[clap(group(ArgGroup::new("start").required(true).args(["transaction", "account"])))]
     pub struct Command {
-        pub component_address: String,
+        /// Start from this transaction
+        #[clap(long, value_parser = parse_transaction_id)]
+        pub transaction: Option<TransactionId>,
+        /// Start from the latest transactions that changed this account's coin balance
+        #[clap(long, value_parser = AccountArg::parse)]
+        pub account: Option<AccountArg>,
+        /// How many of the account's latest transactions to start from
+        #[clap(long, default_value = "5")]
+        pub limit: u32,
+        /// How many transactions away from the start to follow the coins
+        #[clap(long, default_value = "3")]
+        pub depth: u32,
+        #[clap(long, value_enum, default_value = "both")]
+        pub direction: TraceDirection,
+        /// Also write the flow to this file, as Graphviz DOT if it ends in .dot or .gv and as a
+        /// JSON graph otherwise
+        #[clap(long, value_name = "FILE")]
+        pub graph: Option<PathBuf>,
+    }
 
-        pub user_id: String,
+    #[derive(Debug, Clone, Copy, ValueEnum)]
+    pub enum TraceDirection {
+        /// Where the coins went
+        Forward,
+        /// Where the coins came from
+        Backward,
+        Both,
+    }
 
-        pub data: String,
+    impl From<TraceDirection> for Direction {
+        fn from(direction: TraceDirection) -> Self {
+            match direction {
+                TraceDirection::Forward => Direction::Forward,
+                TraceDirection::Backward => Direction::Backward,
+                TraceDirection::Both => Direction::Both,
+            }
+        }
     }
 
     impl Command {
         pub async fn run(
             self,
-            mut client: DaemonClient,
-            dump_buckets: bool,
-            is_dry_run: bool,
-            fees: u64,
-        ) {
-            // let template_address= ;
-            let method = "set_user_data".to_string();
+            client: DaemonClient,
+            indexer: IndexerClient,
+            profile: &Profile,
+            aliases: &BTreeMap<String, String>,
+        ) -> Result<CommandOutput, ClientError> {
+            let coin_resource = resource_address(profile.deployment.coin_resource()?)?;
+            let tracer = Tracer::new(&indexer, coin_resource);
+
+            let roots = match (self.transaction, &self.account) {
+                (Some(transaction_id), _) => vec![transaction_id],
+                (None, Some(account)) => {
+                    let mut roots = vec![];
+                    for (vault_id, vault) in indexer.component_vaults(account.address()?).await? {
+                        if *vault.resource_address() != coin_resource {
+                            continue;
+                        }
+                        for transaction_id in
+                            tracer.recent_transactions(vault_id, self.limit).await?
+                        {
+                            if !roots.contains(&transaction_id) {
+                                roots.push(transaction_id);
+                            }
+                        }
+                    }
+                    roots
+                }
+                (None, None) => unreachable!("clap requires a transaction or an account"),
+            };
+            let trace = tracer
+                .trace(roots, self.depth, self.direction.into())
+                .await?;
+
+            // Vaults are named after the accounts we know of that own them
+            let wallet_accounts = client.accounts().await?;
+            let mut owners = HashMap::<VaultId, String>::new();
+            let known = wallet_accounts
+                .iter()
+                .map(|(_, address)| Ok(*address))
+                .chain(aliases.values().map(|address| component_address(address)))
+                .collect::<Result<Vec<_>, ClientError>>()?;
+            for account in known {
+                let label = super::account_label(&account, aliases, &wallet_accounts);
+                for (vault_id, _) in indexer.component_vaults(account).await? {
+                    owners.entry(vault_id).or_insert_with(|| label.clone());
+                }
+            }
+            let vault_label = |vault: &VaultId| match owners.get(vault) {
+                Some(owner) => format!("{} ({})", owner, vault),
+                None => vault.to_string(),
+            };
+            let amount = |value: i64| format_amount(value, profile.decimals());
+
+            if let Some(path) = &self.graph {
+                let is_dot = path.extension().map_or(false, |ext| {
+                    ext.eq_ignore_ascii_case("dot") || ext.eq_ignore_ascii_case("gv")
+                });
+                let contents = if is_dot {
+                    trace.dot(vault_label, amount)
+                } else {
+                    serde_json::to_string_pretty(&trace.graph(vault_label, amount))
+                        .expect("the graph is serializable")
+                };
+                fs::write(path, contents)?;
+            }
+
+            Ok(
+                CommandOutput::default().with_return_values(serde_json::json!({
+                    "transactions": trace.transactions.len(),
+                    "tree": trace.tree(vault_label, amount),
+                })),
+            )
+        }
+    }
+}
 
-            let mut instructions = vec![];
+pub(crate) mod audit {
+    use crate::output::CommandOutput;
+    use chrono::Utc;
+    use clap::Args;
+    use clap::Subcommand;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use tari_stable_coin_example::amount::format_amount;
+    use tari_stable_coin_example::audit::audit_supply;
+    use tari_stable_coin_example::audit::CoinVault;
+    use tari_stable_coin_example::client::component_address;
+    use tari_stable_coin_example::config::Profile;
+    use tari_stable_coin_example::ClientError;
+    use tari_stable_coin_example::IndexerClient;
+    use tari_stable_coin_example::StablecoinClient;
+    use tari_template_lib::prelude::ComponentAddress;
 
-            instructions.push(Instruction::CallMethod {
-                component_address: ComponentAddress::from_hex(&self.component_address).unwrap(),
-                method,
-                args: args![
-                    parse_arg(&self.user_id).unwrap(),
-                    parse_arg(&self.data).unwrap(),
-                ],
+    /// Reports for auditors
+    #[derive(Debug, Subcommand, Clone)]
+    pub enum Command {
+        /// Compare the total supply with the coins held in the treasury and the known accounts
+        Supply(SupplyArgs),
+    }
+
+    #[derive(Debug, Args, Clone)]
+    pub struct SupplyArgs {
+        /// How many of the largest holders to list
+        #[clap(long, default_value = "10")]
+        pub top: usize,
+        /// Also write the report, with every vault counted, to this file. A name ending in .csv
+        /// gets the vaults as CSV instead
+        #[clap(long, value_name = "FILE")]
+        pub export: Option<PathBuf>,
+    }
+
+    const VAULT_COLUMNS: &[&str] = &[
+        "holder",
+        "account",
+        "vault",
+        "version",
+        "balance",
+        "raw_balance",
+        "blacklisted",
+    ];
+
+    impl Command {
+        pub async fn run(
+            self,
+            mut client: StablecoinClient,
+            indexer: IndexerClient,
+            profile: &Profile,
+            aliases: &BTreeMap<String, String>,
+        ) -> Result<CommandOutput, ClientError> {
+            let Command::Supply(args) = self;
+            let deployment = &profile.deployment;
+            let decimals = profile.decimals();
+            let amount = |value: i64| format_amount(value, decimals);
+
+            // Coins held outside these accounts are reported as the discrepancy
+            let wallet_accounts = client.daemon().accounts().await?;
+            let mut accounts: Vec<_> = wallet_accounts
+                .iter()
+                .map(|(_, address)| *address)
+                .collect();
+            for address in aliases.values() {
+                accounts.push(component_address(address)?);
+            }
+
+            let generated_at = Utc::now();
+            let total_supply = client.total_supply().await?;
+            let journal = profile.journal().entries()?;
+            let audit =
+                audit_supply(&indexer, deployment, total_supply, &accounts, &journal).await?;
+            let label = |address: &ComponentAddress| {
+                super::account_label(address, aliases, &wallet_accounts)
+            };
+
+            let vault_row = |vault: &CoinVault| {
+                serde_json::json!({
+                    "holder": label(&vault.component),
+                    "account": vault.component.to_string(),
+                    "vault": vault.vault.to_string(),
+                    "version": vault.version,
+                    "balance": amount(vault.balance),
+                    "raw_balance": vault.balance,
+                    "blacklisted": vault.blacklisted,
+                })
+            };
+            let holders: Vec<_> = audit
+                .top_holders(args.top)
+                .iter()
+                .map(|(address, balance)| {
+                    serde_json::json!({
+                        "holder": label(address),
+                        "account": address.to_string(),
+                        "balance": amount(*balance),
+                        "raw_balance": balance,
+                    })
+                })
+                .collect();
+            let blacklisted: Vec<_> = audit
+                .holdings
+                .iter()
+                .filter(|vault| vault.blacklisted)
+                .map(vault_row)
+                .collect();
+
+            let values = serde_json::json!({
+                "generated_at": generated_at.to_rfc3339(),
+                "indexer": profile.indexer(),
+                "coin_component": deployment.coin_component()?,
+                "coin_resource": deployment.coin_resource()?,
+                "components_searched": audit.components_searched,
+                "total_supply": amount(audit.total_supply),
+                "treasury": amount(audit.treasury_balance()),
+                "held_by_accounts": amount(audit.holdings_balance()),
+                "accounted_for": amount(audit.accounted()),
+                "discrepancy": amount(audit.discrepancy()),
+                "raw_discrepancy": audit.discrepancy(),
+                "reconciled": audit.discrepancy() == 0,
+                "blacklisted_balance": amount(audit.blacklisted_balance()),
+                "blacklisted_vaults": blacklisted,
+                "unresolved_blacklisted_users": audit
+                    .unresolved_blacklisted
+                    .iter()
+                    .map(super::user_id_value)
+                    .collect::<Vec<_>>(),
+                "top_holders": holders,
             });
 
-            client
-                .submit_instructions(
-                    instructions,
-                    dump_buckets,
-                    is_dry_run,
-                    fees,
-                    vec![format!("component_{}", self.component_address)
-                        .parse()
-                        .unwrap()],
-                )
-                .await;
-            println!("done");
+            if let Some(path) = &args.export {
+                let vaults: Vec<_> = audit
+                    .treasury
+                    .iter()
+                    .chain(&audit.holdings)
+                    .map(vault_row)
+                    .collect();
+                let is_csv = path
+                    .extension()
+                    .map_or(false, |ext| ext.eq_ignore_ascii_case("csv"));
+                if is_csv {
+                    super::export(path, VAULT_COLUMNS, &vaults)?;
+                } else {
+                    let mut report = values.clone();
+                    report["vaults"] = vaults.into();
+                    let contents =
+                        serde_json::to_string_pretty(&report).expect("the report is serializable");
+                    fs::write(path, contents)?;
+                }
+            }
+            Ok(CommandOutput::default().with_return_values(values))
         }
     }
 }
@@ -0,0 +1,503 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use crate::address::parse_component_address;
+use crate::address::parse_resource_address;
+use crate::config::Deployment;
+use crate::config::DEFAULT_MAX_FEE;
+use crate::daemon_client::DaemonClient;
+use crate::daemon_client::TransactionOutcome;
+use crate::error::ClientError;
+use serde::Serialize;
+use tari_engine_types::instruction::Instruction;
+use tari_engine_types::substate::SubstateAddress;
+use tari_template_lib::args;
+use tari_template_lib::args::Arg;
+use tari_template_lib::models::Amount;
+use tari_template_lib::models::NonFungibleAddress;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::SubstateRequirement;
+use tari_transaction::Transaction;
+
+/// Typed access to a deployed stablecoin through the wallet daemon.
+///
+/// Methods that change state return the [`TransactionOutcome`] of the transaction they submit;
+/// a rejected transaction is still `Ok`, so check [`TransactionOutcome::reject_reason`].
+/// Methods that only read state run as dry runs and fail with
+/// [`ClientError::TransactionRejected`] if the query is rejected.
+///
+/// ```no_run
+/// # async fn example(
+/// #     daemon: tari_stable_coin_example::DaemonClient,
+/// #     deployment: tari_stable_coin_example::Deployment,
+/// #     admin: tari_template_lib::prelude::ComponentAddress,
+/// # ) -> Result<(), tari_stable_coin_example::ClientError> {
+/// let mut client = tari_stable_coin_example::StablecoinClient::new(daemon, deployment);
+/// client.mint(admin, 1000).await?;
+/// println!("{}", client.total_supply().await?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StablecoinClient {
+    daemon: DaemonClient,
+    deployment: Deployment,
+    max_fee: u64,
+    dump_buckets: bool,
+    dry_run: bool,
+}
+
+/// The data stored for a user of the stablecoin, as converted to JSON by the wallet daemon.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserData {
+    pub user_id: u64,
+    pub user_data: serde_json::Value,
+}
+
+impl StablecoinClient {
+    pub fn new(daemon: DaemonClient, deployment: Deployment) -> Self {
+        Self {
+            daemon,
+            deployment,
+            max_fee: DEFAULT_MAX_FEE,
+            dump_buckets: false,
+            dry_run: false,
+        }
+    }
+
+    /// The most the fee account may be charged for each transaction.
+    pub fn with_max_fee(mut self, max_fee: u64) -> Self {
+        self.max_fee = max_fee;
+        self
+    }
+
+    /// Deposit any buckets left over at the end of a transaction into the fee account.
+    pub fn with_dump_buckets(mut self, dump_buckets: bool) -> Self {
+        self.dump_buckets = dump_buckets;
+        self
+    }
+
+    /// Execute transactions as dry runs without committing them.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Use a different coin component than the one in the deployment.
+    pub fn with_coin_component(mut self, coin_component: ComponentAddress) -> Self {
+        self.deployment.coin_component = Some(coin_component.to_string());
+        self
+    }
+
+    pub fn daemon(&self) -> &DaemonClient {
+        &self.daemon
+    }
+
+    pub fn deployment(&self) -> &Deployment {
+        &self.deployment
+    }
+
+    /// Mints `amount` new coins into the coin component, proving the right to do so with the
+    /// admin badge held by `admin_account`.
+    pub async fn mint(
+        &mut self,
+        admin_account: ComponentAddress,
+        amount: u64,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(coin_component, "increase_supply", args![amount])
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Burns `amount` coins held by the coin component.
+    pub async fn burn(&mut self, amount: u64) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = vec![Instruction::CallMethod {
+            component_address: coin_component,
+            method: "decrease_supply".to_string(),
+            args: args![amount],
+        }];
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Withdraws `amount` coins from the coin component into `to_account`, proving the right to
+    /// do so with the admin badge held by `admin_account`.
+    pub async fn issue(
+        &mut self,
+        admin_account: ComponentAddress,
+        to_account: ComponentAddress,
+        amount: u64,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let instructions = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(self.coin_component()?, "withdraw", args![amount])
+            .put_last_instruction_output_on_workspace("bucket")
+            .call_method(to_account, "deposit", args![Variable("bucket"),])
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        self.submit_instructions(instructions, vec![]).await
+    }
+
+    /// Mints exactly the coins for `payments` and withdraws them from the coin component into
+    /// each account, all in one transaction, so the new supply is never left undistributed.
+    /// The right to do so is proven with the admin badge held by `admin_account`.
+    pub async fn mint_and_distribute(
+        &mut self,
+        admin_account: ComponentAddress,
+        payments: &[(ComponentAddress, u64)],
+    ) -> Result<TransactionOutcome, ClientError> {
+        let total = payments
+            .iter()
+            .try_fold(0u64, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or_else(|| {
+                ClientError::invalid_argument("amount", "the amounts add up to too many coins")
+            })?;
+        let coin_component = self.coin_component()?;
+        let mut builder = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(coin_component, "increase_supply", args![total]);
+        for (i, &(to_account, amount)) in payments.iter().enumerate() {
+            let bucket = format!("bucket_{}", i);
+            builder = builder
+                .call_method(coin_component, "withdraw", args![amount])
+                .put_last_instruction_output_on_workspace(bucket.as_str())
+                .call_method(
+                    to_account,
+                    "deposit",
+                    vec![Arg::Workspace(bucket.into_bytes())],
+                );
+        }
+        let instructions = builder
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Sends `amount` coins from one account to another. The sending account must hold a user
+    /// badge.
+    pub async fn transfer(
+        &mut self,
+        from_account: ComponentAddress,
+        to_account: ComponentAddress,
+        amount: u64,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let instructions = Transaction::builder()
+            .create_proof(from_account, self.user_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(
+                from_account,
+                "withdraw",
+                args![self.coin_resource()?, amount],
+            )
+            .put_last_instruction_output_on_workspace("bucket")
+            .call_method(to_account, "deposit", args![Variable("bucket"),])
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        let coin_component = self.coin_component()?;
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Sends coins from one account to each of `payments` in a single transaction. Every
+    /// payment withdraws its own amount, and they share the proof and the fee.
+    ///
+    /// Returns once the transaction is submitted, without waiting for it to finalize, so that
+    /// its id can be recorded first. Follow it with [`DaemonClient::transaction_outcome`].
+    pub async fn batch_transfer(
+        &mut self,
+        from_account: ComponentAddress,
+        payments: &[(ComponentAddress, u64)],
+    ) -> Result<TransactionOutcome, ClientError> {
+        let instructions = self.batch_transfer_instructions(from_account, payments)?;
+        let coin_component = self.coin_component()?;
+        self.daemon
+            .without_waiting()
+            .submit_instructions(
+                instructions,
+                self.dump_buckets,
+                self.dry_run,
+                self.max_fee,
+                vec![component_requirement(coin_component)],
+            )
+            .await
+    }
+
+    /// Runs [`Self::batch_transfer`] as a dry run and returns the fee it would cost, failing if
+    /// it would be rejected or cost more than the maximum fee.
+    pub async fn batch_transfer_fee(
+        &mut self,
+        from_account: ComponentAddress,
+        payments: &[(ComponentAddress, u64)],
+    ) -> Result<Amount, ClientError> {
+        let instructions = self.batch_transfer_instructions(from_account, payments)?;
+        let coin_component = self.coin_component()?;
+        let outcome = self
+            .daemon
+            .query_instructions(
+                instructions,
+                self.max_fee,
+                vec![component_requirement(coin_component)],
+            )
+            .await?;
+        if let Some(reason) = outcome.reject_reason() {
+            return Err(ClientError::TransactionRejected(reason));
+        }
+        let fee = outcome
+            .result
+            .as_ref()
+            .map(|result| result.fee_receipt.total_fees_paid())
+            .ok_or_else(|| {
+                ClientError::InvalidResponse("the dry run returned no result".to_string())
+            })?;
+        if fee > Amount::new(self.max_fee as i64) {
+            return Err(ClientError::TransactionRejected(format!(
+                "the fee of {} would be more than the maximum of {}",
+                fee, self.max_fee
+            )));
+        }
+        Ok(fee)
+    }
+
+    fn batch_transfer_instructions(
+        &self,
+        from_account: ComponentAddress,
+        payments: &[(ComponentAddress, u64)],
+    ) -> Result<Vec<Instruction>, ClientError> {
+        let coin_resource = self.coin_resource()?;
+        let mut builder = Transaction::builder()
+            .create_proof(from_account, self.user_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof");
+        // A transaction can only call components and templates, and neither the account nor the
+        // stablecoin template splits a bucket, so each deposit needs a withdrawal of its own
+        for (i, &(to_account, amount)) in payments.iter().enumerate() {
+            let bucket = format!("bucket_{}", i);
+            builder = builder
+                .call_method(from_account, "withdraw", args![coin_resource, amount])
+                .put_last_instruction_output_on_workspace(bucket.as_str())
+                .call_method(
+                    to_account,
+                    "deposit",
+                    vec![Arg::Workspace(bucket.into_bytes())],
+                );
+        }
+        Ok(builder
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions())
+    }
+
+    /// Mints a new admin badge into `new_admin_account`, proving the right to do so with the
+    /// admin badge held by `admin_account`.
+    pub async fn create_admin(
+        &mut self,
+        admin_account: ComponentAddress,
+        new_admin_account: ComponentAddress,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(coin_component, "create_new_admin", args![])
+            .put_last_instruction_output_on_workspace("bucket")
+            .call_method(new_admin_account, "deposit", args![Variable("bucket"),])
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Mints the user badge for `user_id` into `user_account`, proving the right to do so with
+    /// the admin badge held by `admin_account`.
+    pub async fn create_user(
+        &mut self,
+        admin_account: ComponentAddress,
+        user_id: u64,
+        user_account: ComponentAddress,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(coin_component, "create_new_user", args![user_id])
+            .put_last_instruction_output_on_workspace("bucket")
+            .call_method(user_account, "deposit", args![Variable("bucket"),])
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Blacklists `user_id`, whose user badge is held in `badge_vault`, proving the right to do
+    /// so with the admin badge held by `admin_account`.
+    pub async fn blacklist(
+        &mut self,
+        admin_account: ComponentAddress,
+        badge_vault: VaultId,
+        user_id: u64,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let instructions = Transaction::builder()
+            .create_proof(admin_account, self.admin_badge_resource()?)
+            .put_last_instruction_output_on_workspace("proof")
+            .call_method(
+                self.coin_component()?,
+                "blacklist_user",
+                args![badge_vault, user_id],
+            )
+            .drop_all_proofs_in_workspace()
+            .build_as_instructions();
+
+        let inputs = vec![
+            SubstateRequirement::new(SubstateAddress::Vault(badge_vault), None),
+            SubstateRequirement::new(
+                SubstateAddress::NonFungible(NonFungibleAddress::new(
+                    self.user_badge_resource()?,
+                    NonFungibleId::from_u64(user_id),
+                )),
+                None,
+            ),
+        ];
+        self.submit_instructions(instructions, inputs).await
+    }
+
+    pub async fn remove_from_blacklist(
+        &mut self,
+        user_id: u64,
+    ) -> Result<TransactionOutcome, ClientError> {
+        self.call_coin_component("remove_from_blacklist", args![user_id])
+            .await
+    }
+
+    pub async fn set_user_data(
+        &mut self,
+        user_id: u64,
+        data: String,
+    ) -> Result<TransactionOutcome, ClientError> {
+        self.call_coin_component("set_user_data", args![user_id, data])
+            .await
+    }
+
+    pub async fn get_user_data(&mut self, user_id: u64) -> Result<UserData, ClientError> {
+        let outcome = self
+            .query_coin_component("get_user_data", args![user_id])
+            .await?;
+        Ok(UserData {
+            user_id,
+            user_data: outcome.json_return_value()?,
+        })
+    }
+
+    pub async fn total_supply(&mut self) -> Result<Amount, ClientError> {
+        let outcome = self.query_coin_component("total_supply", args![]).await?;
+        outcome.decode_return_value()
+    }
+
+    async fn call_coin_component(
+        &mut self,
+        method: &str,
+        args: Vec<Arg>,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = vec![Instruction::CallMethod {
+            component_address: coin_component,
+            method: method.to_string(),
+            args,
+        }];
+
+        self.submit_instructions(instructions, vec![component_requirement(coin_component)])
+            .await
+    }
+
+    /// Calls `method` on the coin component as a dry run, failing if the call was rejected.
+    async fn query_coin_component(
+        &mut self,
+        method: &str,
+        args: Vec<Arg>,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let coin_component = self.coin_component()?;
+        let instructions = vec![Instruction::CallMethod {
+            component_address: coin_component,
+            method: method.to_string(),
+            args,
+        }];
+
+        let outcome = self
+            .daemon
+            .query_instructions(
+                instructions,
+                self.max_fee,
+                vec![component_requirement(coin_component)],
+            )
+            .await?;
+        match outcome.reject_reason() {
+            Some(reason) => Err(ClientError::TransactionRejected(reason)),
+            None => Ok(outcome),
+        }
+    }
+
+    /// Submits instructions the typed methods do not cover, with this client's fee and dry run
+    /// settings.
+    pub async fn submit_instructions(
+        &mut self,
+        instructions: Vec<Instruction>,
+        inputs: Vec<SubstateRequirement>,
+    ) -> Result<TransactionOutcome, ClientError> {
+        self.daemon
+            .submit_instructions(
+                instructions,
+                self.dump_buckets,
+                self.dry_run,
+                self.max_fee,
+                inputs,
+            )
+            .await
+    }
+
+    fn coin_component(&self) -> Result<ComponentAddress, ClientError> {
+        component_address(self.deployment.coin_component()?)
+    }
+
+    fn admin_badge_resource(&self) -> Result<ResourceAddress, ClientError> {
+        resource_address(self.deployment.admin_badge_resource()?)
+    }
+
+    fn user_badge_resource(&self) -> Result<ResourceAddress, ClientError> {
+        resource_address(self.deployment.user_badge_resource()?)
+    }
+
+    fn coin_resource(&self) -> Result<ResourceAddress, ClientError> {
+        resource_address(self.deployment.coin_resource()?)
+    }
+}
+
+/// Parses an address taken from the config or a spec file rather than the command line.
+pub fn component_address(value: &str) -> Result<ComponentAddress, ClientError> {
+    parse_component_address(value).map_err(|reason| ClientError::invalid_address(value, reason))
+}
+
+/// Parses an address taken from the config or a spec file rather than the command line.
+pub fn resource_address(value: &str) -> Result<ResourceAddress, ClientError> {
+    parse_resource_address(value).map_err(|reason| ClientError::invalid_address(value, reason))
+}
+
+pub fn component_requirement(address: ComponentAddress) -> SubstateRequirement {
+    SubstateRequirement::new(SubstateAddress::Component(address), None)
+}
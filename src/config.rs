@@ -0,0 +1,257 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use crate::error::ClientError;
+use crate::journal::Journal;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub const CONFIG_FILE_NAME: &str = "stablecoin.toml";
+pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:18016";
+pub const DEFAULT_INDEXER_ENDPOINT: &str = "http://127.0.0.1:18300";
+pub const DEFAULT_ACCOUNT: &str = "TestAccount_0";
+pub const DEFAULT_MAX_FEE: u64 = 1500;
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The contents of `stablecoin.toml`.
+///
+/// ```toml
+/// default_profile = "local"
+///
+/// [profiles.local]
+/// endpoint = "http://127.0.0.1:18016"
+/// indexer = "http://127.0.0.1:18300"
+/// account = "TestAccount_0"
+/// max_fee = 1500
+/// decimals = 2
+/// template = "0x6c65..."
+/// coin_component = "component_ea50..."
+/// admin_badge_resource = "resource_bc5a..."
+/// user_badge_resource = "resource_d7d5..."
+/// coin_resource = "resource_7af4..."
+///
+/// [aliases]
+/// treasury = "component_a266..."
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The address book, mapping names to account component addresses
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// JSON-RPC endpoint of the indexer, for commands that read network state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee: Option<u64>,
+    /// Number of decimal places the coin is displayed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u32>,
+    /// Where to record blacklist changes, instead of the user data directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal: Option<PathBuf>,
+    #[serde(flatten)]
+    pub deployment: Deployment,
+}
+
+/// The addresses of a deployed stablecoin template and the component and resources created by
+/// instantiating it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Deployment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coin_component: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_badge_resource: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_badge_resource: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coin_resource: Option<String>,
+}
+
+/// A config file and the location it was loaded from.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    pub path: Option<PathBuf>,
+    pub config: Config,
+}
+
+impl ConfigFile {
+    /// Loads the config from `path` if given, otherwise from `stablecoin.toml` in the working
+    /// directory and then the user config directory. No file at all is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self, ClientError> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => Self::search_paths().into_iter().find(|p| p.exists()),
+        };
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|e| {
+            ClientError::Config(format!("could not read {}: {}", path.display(), e))
+        })?;
+        let config = toml::from_str(&contents).map_err(|e| {
+            ClientError::Config(format!("could not parse {}: {}", path.display(), e))
+        })?;
+        Ok(Self {
+            path: Some(path),
+            config,
+        })
+    }
+
+    fn search_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(CONFIG_FILE_NAME)];
+        if let Some(dir) = dirs::config_dir() {
+            paths.push(dir.join("tari_stablecoin").join(CONFIG_FILE_NAME));
+        }
+        paths
+    }
+
+    /// Writes the config back to the file it was loaded from, or to `stablecoin.toml` in the
+    /// working directory if there was none.
+    pub fn save(&mut self) -> Result<PathBuf, ClientError> {
+        let path = self
+            .path
+            .get_or_insert_with(|| PathBuf::from(CONFIG_FILE_NAME))
+            .clone();
+        let contents = toml::to_string_pretty(&self.config)
+            .map_err(|e| ClientError::Config(format!("could not serialize config: {}", e)))?;
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Replaces the deployment addresses of the named profile and saves the file.
+    pub fn save_deployment(
+        &mut self,
+        profile_name: &str,
+        deployment: &Deployment,
+    ) -> Result<PathBuf, ClientError> {
+        self.profile_entry(profile_name).deployment = deployment.clone();
+        self.save()
+    }
+
+    /// Sets the template address of the named profile and saves the file.
+    pub fn save_template(
+        &mut self,
+        profile_name: &str,
+        template: String,
+    ) -> Result<PathBuf, ClientError> {
+        self.profile_entry(profile_name).deployment.template = Some(template);
+        self.save()
+    }
+
+    /// Returns the named profile for editing, creating it if needed. The first profile written
+    /// to a file without a default becomes the default.
+    fn profile_entry(&mut self, profile_name: &str) -> &mut Profile {
+        if self.config.default_profile.is_none() {
+            self.config.default_profile = Some(profile_name.to_string());
+        }
+        self.config
+            .profiles
+            .entry(profile_name.to_string())
+            .or_default()
+    }
+
+    /// The name of the profile to use: `name` if given, otherwise the file's default profile.
+    pub fn profile_name(&self, name: Option<&str>) -> Option<String> {
+        name.map(|n| n.to_string())
+            .or_else(|| self.config.default_profile.clone())
+    }
+
+    /// Returns the named profile. A profile that was asked for by name must exist.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, ClientError> {
+        match self.profile_name(name) {
+            Some(name) => self.config.profiles.get(&name).cloned().ok_or_else(|| {
+                ClientError::Config(format!(
+                    "profile '{}' not found in {}",
+                    name,
+                    self.path
+                        .as_ref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|| "any config file".to_string())
+                ))
+            }),
+            None => Ok(Profile::default()),
+        }
+    }
+}
+
+impl Profile {
+    pub fn endpoint(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+    }
+
+    pub fn indexer(&self) -> String {
+        self.indexer
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INDEXER_ENDPOINT.to_string())
+    }
+
+    pub fn account(&self) -> String {
+        self.account
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ACCOUNT.to_string())
+    }
+
+    pub fn max_fee(&self) -> u64 {
+        self.max_fee.unwrap_or(DEFAULT_MAX_FEE)
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals.unwrap_or(0)
+    }
+
+    pub fn journal(&self) -> Journal {
+        Journal::new(self.journal.clone().unwrap_or_else(Journal::default_path))
+    }
+}
+
+impl Deployment {
+    pub fn template(&self) -> Result<&str, ClientError> {
+        required(&self.template, "template")
+    }
+
+    pub fn coin_component(&self) -> Result<&str, ClientError> {
+        required(&self.coin_component, "coin_component")
+    }
+
+    pub fn admin_badge_resource(&self) -> Result<&str, ClientError> {
+        required(&self.admin_badge_resource, "admin_badge_resource")
+    }
+
+    pub fn user_badge_resource(&self) -> Result<&str, ClientError> {
+        required(&self.user_badge_resource, "user_badge_resource")
+    }
+
+    pub fn coin_resource(&self) -> Result<&str, ClientError> {
+        required(&self.coin_resource, "coin_resource")
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, ClientError> {
+    value.as_deref().ok_or_else(|| {
+        ClientError::Config(format!(
+            "no {} configured, set it in the profile or pass it on the command line",
+            name
+        ))
+    })
+}
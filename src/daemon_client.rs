@@ -1,47 +1,329 @@
 //  Copyright 2023 The Tari Project
 //  SPDX-License-Identifier: BSD-3-Clause
 
+use crate::error::ClientError;
+use crate::prepared::PreparedTransaction;
+use chrono::NaiveDateTime;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+use tari_dan_wallet_sdk::models::TransactionStatus;
+use tari_engine_types::commit_result::FinalizeResult;
+use tari_engine_types::commit_result::TransactionResult;
 use tari_engine_types::instruction::Instruction;
+use tari_engine_types::substate::SubstateAddress;
+use tari_template_lib::models::Amount;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::ResourceAddress;
 use tari_transaction::SubstateRequirement;
+use tari_transaction::Transaction;
+use tari_transaction::TransactionId;
+use tari_wallet_daemon_client::types::AccountsGetBalancesRequest;
 use tari_wallet_daemon_client::types::AuthLoginRequest;
 use tari_wallet_daemon_client::types::CallInstructionRequest;
+use tari_wallet_daemon_client::types::TransactionGetAllRequest;
+use tari_wallet_daemon_client::types::TransactionWaitResultRequest;
 
 use tari_utilities::hex::Hex;
 use tari_wallet_daemon_client::ComponentAddressOrName;
 use tari_wallet_daemon_client::WalletDaemonClient;
+
+/// How long a single status request may block in the daemon before we ask again.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// More accounts than any wallet this is used with will have
+const MAX_ACCOUNTS: u64 = 1000;
+
+#[derive(Debug, Clone)]
 pub struct DaemonClient {
     endpoint: String,
     auth_token: Option<String>,
     default_account: String,
+    wait_timeout: Option<Duration>,
+    prepare_to: Option<PathBuf>,
+}
+
+/// What is known about a submitted transaction. `status` is only set once the daemon has
+/// reported on the transaction, which does not happen when submitting with `--no-wait`.
+/// A transaction written to a file with `--prepare` has no id until it is submitted.
+#[derive(Debug, Clone)]
+pub struct TransactionOutcome {
+    pub transaction_id: Option<TransactionId>,
+    pub prepared: Option<PathBuf>,
+    pub status: Option<TransactionStatus>,
+    pub final_fee: Option<Amount>,
+    pub result: Option<FinalizeResult>,
+    pub json_result: Option<Vec<serde_json::Value>>,
+    pub timed_out: bool,
+}
+
+/// The balance of one vault of an account.
+#[derive(Debug, Clone)]
+pub struct VaultBalance {
+    pub vault: SubstateAddress,
+    pub resource: ResourceAddress,
+    pub balance: Amount,
+}
+
+/// A transaction from the wallet's history.
+#[derive(Debug, Clone)]
+pub struct WalletTransaction {
+    pub transaction: Transaction,
+    pub result: Option<FinalizeResult>,
+    pub status: TransactionStatus,
+    /// When the wallet last updated the transaction
+    pub time: NaiveDateTime,
+}
+
+impl TransactionOutcome {
+    fn submitted(transaction_id: TransactionId) -> Self {
+        Self {
+            transaction_id: Some(transaction_id),
+            prepared: None,
+            status: None,
+            final_fee: None,
+            result: None,
+            json_result: None,
+            timed_out: false,
+        }
+    }
+
+    fn prepared(path: PathBuf) -> Self {
+        Self {
+            transaction_id: None,
+            prepared: Some(path),
+            status: None,
+            final_fee: None,
+            result: None,
+            json_result: None,
+            timed_out: false,
+        }
+    }
+
+    pub fn transaction_id_hex(&self) -> Option<String> {
+        self.transaction_id
+            .as_ref()
+            .map(|id| id.as_bytes().to_vec().to_hex())
+    }
+
+    /// The transaction status as reported by the daemon, or what we know about it locally if
+    /// the daemon has not reported on it.
+    pub fn status_name(&self) -> String {
+        match &self.status {
+            Some(status) => format!("{:?}", status),
+            None if self.prepared.is_some() => "Prepared".to_string(),
+            None if self.result.is_some() => "DryRun".to_string(),
+            None => "Submitted".to_string(),
+        }
+    }
+
+    /// Whether the transaction was submitted to be committed and has not been rejected so far.
+    /// Dry runs and prepared transactions never are.
+    pub fn is_submitted(&self) -> bool {
+        let dry_run = self.status.is_none() && self.result.is_some();
+        self.transaction_id.is_some()
+            && self.prepared.is_none()
+            && !dry_run
+            && self.reject_reason().is_none()
+    }
+
+    /// Decodes the value returned by the last instruction of the transaction.
+    pub fn decode_return_value<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        let result = self
+            .result
+            .as_ref()
+            .and_then(|r| r.execution_results.last())
+            .ok_or_else(|| {
+                ClientError::InvalidResponse("the transaction did not return a value".to_string())
+            })?;
+        result.decode().map_err(|e| {
+            ClientError::InvalidResponse(format!("could not decode the return value: {}", e))
+        })
+    }
+
+    /// The value returned by the last instruction of the transaction, as converted to JSON by
+    /// the wallet daemon.
+    pub fn json_return_value(&self) -> Result<serde_json::Value, ClientError> {
+        self.json_result
+            .as_ref()
+            .and_then(|values| values.last())
+            .cloned()
+            .ok_or_else(|| {
+                ClientError::InvalidResponse("the transaction did not return a value".to_string())
+            })
+    }
+
+    /// The reason the transaction was rejected, if it was.
+    pub fn reject_reason(&self) -> Option<String> {
+        match self.result.as_ref().map(|r| &r.result) {
+            Some(TransactionResult::Reject(reason))
+            | Some(TransactionResult::AcceptFeeRejectRest(_, reason)) => Some(reason.to_string()),
+            _ => match self.status {
+                Some(TransactionStatus::Rejected)
+                | Some(TransactionStatus::InvalidTransaction)
+                | Some(TransactionStatus::OnlyFeeAccepted) => Some(format!(
+                    "transaction finished with status {:?}",
+                    self.status
+                )),
+                _ => None,
+            },
+        }
+    }
 }
 
 impl DaemonClient {
-    pub(crate) fn new(
+    pub fn new(
         endpoint: String,
         auth_token: Option<String>,
         default_account: String,
+        wait_timeout: Option<Duration>,
     ) -> Self {
         Self {
             endpoint,
             auth_token,
             default_account,
+            wait_timeout,
+            prepare_to: None,
+        }
+    }
+
+    /// Write transactions to `path` for later submission instead of submitting them. Queries
+    /// are still run against the daemon.
+    pub fn with_prepare(mut self, path: PathBuf) -> Self {
+        self.prepare_to = Some(path);
+        self
+    }
+
+    /// A client that returns as soon as a transaction is submitted, for callers that record its
+    /// id before following it with [`Self::transaction_outcome`].
+    pub fn without_waiting(&self) -> Self {
+        Self {
+            wait_timeout: None,
+            ..self.clone()
         }
     }
 
-    pub async fn login(&mut self) -> String {
-        let mut client =
-            WalletDaemonClient::connect(&self.endpoint, self.auth_token.clone()).unwrap();
+    /// Whether transactions are written to a file instead of being submitted.
+    pub fn is_preparing(&self) -> bool {
+        self.prepare_to.is_some()
+    }
+
+    /// The name of the wallet account that pays the fees.
+    pub fn default_account(&self) -> &str {
+        &self.default_account
+    }
+
+    /// Whether submitting a transaction returns its final result.
+    pub fn waits_for_finalization(&self) -> bool {
+        self.wait_timeout.is_some() && self.prepare_to.is_none()
+    }
+
+    fn connect(&self) -> Result<WalletDaemonClient, ClientError> {
+        WalletDaemonClient::connect(&self.endpoint, self.auth_token.clone())
+            .map_err(|e| ClientError::Connection(format!("{}: {}", self.endpoint, e)))
+    }
+
+    pub async fn login(&mut self) -> Result<String, ClientError> {
+        let mut client = self.connect()?;
         let r = client
             .auth_request(&AuthLoginRequest {
                 permissions: vec!["Admin".to_string()],
                 duration: None,
             })
             .await
-            .unwrap();
+            .map_err(|e| ClientError::from_daemon(e, ClientError::Authentication))?;
+
+        Ok(r.auth_token)
+    }
+
+    /// Looks up the component address of the wallet account called `name`.
+    pub async fn account_address(&self, name: &str) -> Result<ComponentAddress, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .accounts_get(ComponentAddressOrName::Name(name.to_string()))
+            .await
+            .map_err(|e| {
+                ClientError::from_daemon(e, |e| {
+                    ClientError::invalid_address(
+                        name,
+                        format!("not an address, alias or account in the wallet ({})", e),
+                    )
+                })
+            })?;
+        r.account.address.as_component_address().ok_or_else(|| {
+            ClientError::InvalidResponse(format!(
+                "account '{}' has address {}, which is not a component",
+                name, r.account.address
+            ))
+        })
+    }
+
+    /// The name and address of every account in the wallet.
+    pub async fn accounts(&self) -> Result<Vec<(String, ComponentAddress)>, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .list_accounts(0, MAX_ACCOUNTS)
+            .await
+            .map_err(|e| ClientError::from_daemon(e, ClientError::InvalidResponse))?;
+
+        Ok(r.accounts
+            .into_iter()
+            .filter_map(|info| {
+                let address = info.account.address.as_component_address()?;
+                Some((info.account.name, address))
+            })
+            .collect())
+    }
+
+    /// The balances of every vault of `account`, or of the default account if none is given,
+    /// as last seen on the network.
+    pub async fn account_balances(
+        &self,
+        account: Option<ComponentAddress>,
+    ) -> Result<Vec<VaultBalance>, ClientError> {
+        let mut client = self.connect()?;
+        let account = match account {
+            Some(address) => ComponentAddressOrName::ComponentAddress(address),
+            None => ComponentAddressOrName::Name(self.default_account.clone()),
+        };
+        let r = client
+            .get_account_balances(AccountsGetBalancesRequest {
+                account,
+                refresh: true,
+            })
+            .await
+            .map_err(|e| ClientError::from_daemon(e, ClientError::InvalidResponse))?;
 
-        dbg!(&r);
+        Ok(r.balances
+            .into_iter()
+            .map(|entry| VaultBalance {
+                vault: entry.vault_address,
+                resource: entry.resource_address,
+                balance: entry.balance,
+            })
+            .collect())
+    }
 
-        r.auth_token
+    /// Every transaction the wallet knows about.
+    pub async fn transactions(&self) -> Result<Vec<WalletTransaction>, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .list_transactions(TransactionGetAllRequest {
+                status: None,
+                component: None,
+            })
+            .await
+            .map_err(|e| ClientError::from_daemon(e, ClientError::InvalidResponse))?;
+
+        Ok(r.transactions
+            .into_iter()
+            .map(|(transaction, result, status, time)| WalletTransaction {
+                transaction,
+                result,
+                status,
+                time,
+            })
+            .collect())
     }
 
     pub async fn submit_instruction(
@@ -51,7 +333,7 @@ impl DaemonClient {
         is_dry_run: bool,
         fees: u64,
         other_inputs: Vec<SubstateRequirement>,
-    ) {
+    ) -> Result<TransactionOutcome, ClientError> {
         self.submit_instructions(
             vec![instruction],
             dump_buckets,
@@ -59,7 +341,7 @@ impl DaemonClient {
             fees,
             other_inputs,
         )
-        .await;
+        .await
     }
 
     pub async fn submit_instructions(
@@ -69,14 +351,59 @@ impl DaemonClient {
         is_dry_run: bool,
         max_fee: u64,
         other_inputs: Vec<SubstateRequirement>,
-    ) {
-        let mut client =
-            WalletDaemonClient::connect(&self.endpoint, self.auth_token.clone()).unwrap();
-        //let r = client.list_keys().await;
+    ) -> Result<TransactionOutcome, ClientError> {
+        let request = self.request(
+            instructions,
+            dump_buckets,
+            is_dry_run,
+            max_fee,
+            other_inputs,
+        );
+        match &self.prepare_to {
+            Some(path) => {
+                PreparedTransaction::new(request).save(path)?;
+                Ok(TransactionOutcome::prepared(path.clone()))
+            }
+            None => self.submit_request(request).await,
+        }
+    }
+
+    /// Submits a complete request, such as one loaded from a prepared transaction file.
+    pub async fn submit_request(
+        &mut self,
+        request: CallInstructionRequest,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let mut client = self.connect()?;
+        let is_dry_run = request.is_dry_run;
 
-        //dbg!(r);
+        let r2 = client
+            .submit_instruction(request)
+            .await
+            .map_err(|e| ClientError::from_daemon(e, ClientError::TransactionRejected))?;
 
-        let tx = CallInstructionRequest {
+        let mut outcome = TransactionOutcome::submitted(r2.transaction_id);
+        outcome.result = r2.result.map(|r| r.finalize);
+        outcome.json_result = r2.json_result;
+
+        // Dry runs are executed by the daemon immediately and never reach the validators
+        match self.wait_timeout {
+            Some(timeout) if !is_dry_run => {
+                self.wait_for_finalization(&mut client, r2.transaction_id, outcome, timeout)
+                    .await
+            }
+            _ => Ok(outcome),
+        }
+    }
+
+    fn request(
+        &self,
+        instructions: Vec<Instruction>,
+        dump_buckets: bool,
+        is_dry_run: bool,
+        max_fee: u64,
+        other_inputs: Vec<SubstateRequirement>,
+    ) -> CallInstructionRequest {
+        CallInstructionRequest {
             instructions,
             fee_account: ComponentAddressOrName::Name(self.default_account.clone()),
             dump_outputs_into: if dump_buckets {
@@ -92,19 +419,64 @@ impl DaemonClient {
             new_outputs: None,
             min_epoch: None,
             max_epoch: None,
-        };
+        }
+    }
 
-        let r2 = client.submit_instruction(tx).await.unwrap();
+    /// Looks up a transaction submitted earlier, waiting for it to finalize like a new
+    /// submission would.
+    pub async fn transaction_outcome(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let mut client = self.connect()?;
+        let outcome = TransactionOutcome::submitted(transaction_id);
+        let timeout = self.wait_timeout.unwrap_or(POLL_INTERVAL);
+        self.wait_for_finalization(&mut client, transaction_id, outcome, timeout)
+            .await
+    }
 
-        dbg!(r2.transaction_id.as_bytes().to_vec().to_hex());
-        //"dump_outputs_into": self.default_account,
+    /// Runs `instructions` as a dry run. The daemon executes them against the current state and
+    /// returns the result, but the transaction is never committed so no fees are paid.
+    pub async fn query_instructions(
+        &mut self,
+        instructions: Vec<Instruction>,
+        max_fee: u64,
+        other_inputs: Vec<SubstateRequirement>,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let request = self.request(instructions, false, true, max_fee, other_inputs);
+        self.submit_request(request).await
     }
 
-    //  {
-    //    "instruction": instruction,
-    //  "fee_account": self.last_account_name,
-    //     "dump_outputs_into": self.last_account_name,
-    //     "fee": 1000,
-    // },
-    //
+    /// Polls the daemon until the transaction is finalized or `timeout` has elapsed.
+    async fn wait_for_finalization(
+        &self,
+        client: &mut WalletDaemonClient,
+        transaction_id: TransactionId,
+        mut outcome: TransactionOutcome,
+        timeout: Duration,
+    ) -> Result<TransactionOutcome, ClientError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let r = client
+                .wait_transaction_result(TransactionWaitResultRequest {
+                    transaction_id,
+                    timeout_secs: Some(remaining.min(POLL_INTERVAL).as_secs().max(1)),
+                })
+                .await
+                .map_err(|e| ClientError::from_daemon(e, ClientError::InvalidResponse))?;
+
+            outcome.status = Some(r.status);
+            if !r.timed_out {
+                outcome.final_fee = Some(r.final_fee);
+                outcome.result = r.result;
+                outcome.json_result = r.json_result;
+                return Ok(outcome);
+            }
+            if Instant::now() >= deadline {
+                outcome.timed_out = true;
+                return Ok(outcome);
+            }
+        }
+    }
 }
@@ -0,0 +1,135 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use reqwest::StatusCode;
+use std::error::Error as StdError;
+use tari_wallet_daemon_client::error::WalletDaemonClientError;
+
+/// The codes the wallet daemon answers with when the auth token is missing, invalid or lacks
+/// the permission for a request
+const UNAUTHORIZED: i32 = 401;
+const FORBIDDEN: i32 = 403;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("Could not reach the wallet daemon: {0}")]
+    Connection(String),
+    #[error("Authentication with the wallet daemon failed: {0}")]
+    Authentication(String),
+    #[error("Transaction was rejected: {0}")]
+    TransactionRejected(String),
+    #[error("Transaction did not finalize in time: {0}")]
+    TimedOut(String),
+    #[error("Invalid address '{value}': {reason}")]
+    InvalidAddress { value: String, reason: String },
+    #[error("Invalid argument '{name}': {reason}")]
+    InvalidArgument { name: String, reason: String },
+    #[error("Unexpected response from the wallet daemon: {0}")]
+    InvalidResponse(String),
+    #[error("Indexer request failed: {0}")]
+    Indexer(String),
+    #[error("Configuration error: {0}")]
+    Config(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl ClientError {
+    pub fn invalid_address<T: Into<String>, R: Into<String>>(value: T, reason: R) -> Self {
+        Self::InvalidAddress {
+            value: value.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn invalid_argument<T: Into<String>, R: Into<String>>(name: T, reason: R) -> Self {
+        Self::InvalidArgument {
+            name: name.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Classifies an error returned by the wallet daemon client. Anything that failed before
+    /// the daemon could answer is reported as a connection error and a refused auth token as an
+    /// authentication error. Other errors the daemon answered with are passed to `otherwise`,
+    /// and answers that could not be read are invalid responses.
+    pub fn from_daemon<E: StdError + 'static, F: FnOnce(String) -> Self>(
+        err: E,
+        otherwise: F,
+    ) -> Self {
+        let message = err.to_string();
+        match classify(&err) {
+            DaemonFailure::Connection => Self::Connection(message),
+            DaemonFailure::Unauthorized => Self::Authentication(message),
+            DaemonFailure::Answered => otherwise(message),
+            DaemonFailure::Unreadable => Self::InvalidResponse(message),
+        }
+    }
+
+    /// A stable name for the kind of error, used in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Connection(_) => "connection",
+            Self::Authentication(_) => "authentication",
+            Self::TransactionRejected(_) => "transaction_rejected",
+            Self::TimedOut(_) => "timed_out",
+            Self::InvalidAddress { .. } => "invalid_address",
+            Self::InvalidArgument { .. } => "invalid_argument",
+            Self::Io(_) => "io",
+            Self::InvalidResponse(_) => "invalid_response",
+            Self::Config(_) => "config",
+            Self::Indexer(_) => "indexer",
+        }
+    }
+
+    /// The process exit code for this error, so that scripts can branch on the failure type.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Connection(_) => 3,
+            Self::Authentication(_) => 4,
+            Self::TransactionRejected(_) => 5,
+            Self::InvalidAddress { .. } => 6,
+            Self::InvalidArgument { .. } => 7,
+            Self::Io(_) => 8,
+            Self::InvalidResponse(_) => 9,
+            Self::Config(_) => 10,
+            Self::Indexer(_) => 11,
+            Self::TimedOut(_) => 12,
+        }
+    }
+}
+
+enum DaemonFailure {
+    Connection,
+    Unauthorized,
+    /// The daemon returned an error of its own, such as refusing a transaction
+    Answered,
+    Unreadable,
+}
+
+fn classify(err: &(dyn StdError + 'static)) -> DaemonFailure {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+            if err.is_connect() || err.is_timeout() {
+                return DaemonFailure::Connection;
+            }
+            return match err.status() {
+                Some(StatusCode::UNAUTHORIZED) | Some(StatusCode::FORBIDDEN) => {
+                    DaemonFailure::Unauthorized
+                }
+                _ => DaemonFailure::Unreadable,
+            };
+        }
+        if let Some(WalletDaemonClientError::RequestFailedWithStatus { code, .. }) =
+            err.downcast_ref::<WalletDaemonClientError>()
+        {
+            return match *code {
+                UNAUTHORIZED | FORBIDDEN => DaemonFailure::Unauthorized,
+                _ => DaemonFailure::Answered,
+            };
+        }
+        current = err.source();
+    }
+    DaemonFailure::Unreadable
+}
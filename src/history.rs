@@ -0,0 +1,154 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Works out how a transaction moved coins from its instructions, for the shapes of transaction
+//! the stablecoin commands build: an account or the coin component withdraws into a bucket that
+//! is put on the workspace, and the bucket is deposited into another account.
+
+use std::collections::HashMap;
+use std::fmt;
+use tari_engine_types::instruction::Instruction;
+use tari_template_lib::args::Arg;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::ResourceAddress;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovementKind {
+    /// `increase_supply` on the coin component
+    Mint,
+    /// `decrease_supply` on the coin component
+    Burn,
+    /// Coins withdrawn from the coin component by an admin
+    Issue,
+    /// Coins withdrawn from one account and deposited into another
+    Transfer,
+    /// Any other call on the coin component, e.g. `create_new_user`
+    Call(String),
+}
+
+impl fmt::Display for MovementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovementKind::Mint => write!(f, "mint"),
+            MovementKind::Burn => write!(f, "burn"),
+            MovementKind::Issue => write!(f, "issue"),
+            MovementKind::Transfer => write!(f, "transfer"),
+            MovementKind::Call(method) => write!(f, "{}", method),
+        }
+    }
+}
+
+/// One movement of coins, or one call on the coin component, within a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinMovement {
+    pub kind: MovementKind,
+    pub from: Option<ComponentAddress>,
+    pub to: Option<ComponentAddress>,
+    pub amount: Option<u64>,
+}
+
+impl CoinMovement {
+    fn new(kind: MovementKind, from: Option<ComponentAddress>, amount: Option<u64>) -> Self {
+        Self {
+            kind,
+            from,
+            to: None,
+            amount,
+        }
+    }
+
+    pub fn involves(&self, account: &ComponentAddress) -> bool {
+        self.from.as_ref() == Some(account) || self.to.as_ref() == Some(account)
+    }
+}
+
+/// The coin movements and coin component calls in `instructions`, in order. Transactions that
+/// touch neither the coin component nor `coin_resource` have none.
+pub fn coin_movements(
+    instructions: &[Instruction],
+    coin_component: &ComponentAddress,
+    coin_resource: &ResourceAddress,
+) -> Vec<CoinMovement> {
+    let mut movements: Vec<CoinMovement> = vec![];
+    // The movement whose bucket was returned by the last instruction, and the workspace keys
+    // the buckets were put under
+    let mut last_withdrawal = None;
+    let mut buckets = HashMap::new();
+
+    for instruction in instructions {
+        let mut withdrawal = None;
+        match instruction {
+            Instruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } if component_address == coin_component => {
+                let movement = match method.as_str() {
+                    "increase_supply" => CoinMovement::new(MovementKind::Mint, None, amount(args)),
+                    "decrease_supply" => CoinMovement::new(MovementKind::Burn, None, amount(args)),
+                    "withdraw" => {
+                        withdrawal = Some(movements.len());
+                        CoinMovement::new(
+                            MovementKind::Issue,
+                            Some(*component_address),
+                            amount(args),
+                        )
+                    }
+                    method => CoinMovement::new(MovementKind::Call(method.to_string()), None, None),
+                };
+                movements.push(movement);
+            }
+            Instruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } if method == "withdraw" && resource(args) == Some(*coin_resource) => {
+                withdrawal = Some(movements.len());
+                movements.push(CoinMovement::new(
+                    MovementKind::Transfer,
+                    Some(*component_address),
+                    args.get(1).and_then(literal_u64),
+                ));
+            }
+            Instruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } if method == "deposit" => {
+                let deposited = match args.first() {
+                    Some(Arg::Workspace(key)) => buckets.remove(key),
+                    _ => None,
+                };
+                if let Some(index) = deposited {
+                    movements[index].to = Some(*component_address);
+                }
+            }
+            Instruction::PutLastInstructionOutputOnWorkspace { key } => {
+                if let Some(index) = last_withdrawal {
+                    buckets.insert(key.clone(), index);
+                }
+            }
+            _ => {}
+        }
+        last_withdrawal = withdrawal;
+    }
+    movements
+}
+
+fn amount(args: &[Arg]) -> Option<u64> {
+    args.first().and_then(literal_u64)
+}
+
+fn resource(args: &[Arg]) -> Option<ResourceAddress> {
+    match args.first() {
+        Some(Arg::Literal(bytes)) => tari_bor::decode(bytes).ok(),
+        _ => None,
+    }
+}
+
+fn literal_u64(arg: &Arg) -> Option<u64> {
+    match arg {
+        Arg::Literal(bytes) => tari_bor::decode(bytes).ok(),
+        _ => None,
+    }
+}
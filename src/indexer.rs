@@ -0,0 +1,203 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use crate::error::ClientError;
+use reqwest::Url;
+use tari_engine_types::component::ComponentHeader;
+use tari_engine_types::indexed_value::IndexedValue;
+use tari_engine_types::non_fungible::NonFungibleContainer;
+use tari_engine_types::substate::SubstateAddress;
+use tari_engine_types::substate::SubstateDiff;
+use tari_engine_types::substate::SubstateValue;
+use tari_engine_types::vault::Vault;
+use tari_indexer_client::json_rpc_client::IndexerJsonRpcClient;
+use tari_indexer_client::types::GetNonFungibleCountRequest;
+use tari_indexer_client::types::GetNonFungiblesRequest;
+use tari_indexer_client::types::GetSubstateRequest;
+use tari_indexer_client::types::GetTransactionResultRequest;
+use tari_indexer_client::types::IndexerTransactionFinalizedResult;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+
+/// A version of a substate and the transaction that created it.
+#[derive(Debug, Clone)]
+pub struct VersionedSubstate {
+    pub value: SubstateValue,
+    pub version: u32,
+    pub created_by: TransactionId,
+}
+
+/// Reads committed network state from a `tari_indexer`, for the commands that need more than
+/// the wallet knows about.
+///
+/// The indexer does not list the holders of a resource, so tokens can only be found in the
+/// components we already know of: the wallet's accounts and those in the address book.
+#[derive(Debug, Clone)]
+pub struct IndexerClient {
+    endpoint: String,
+}
+
+impl IndexerClient {
+    pub fn new(endpoint: String) -> Self {
+        Self { endpoint }
+    }
+
+    fn connect(&self) -> Result<IndexerJsonRpcClient, ClientError> {
+        let url = Url::parse(&self.endpoint).map_err(|e| {
+            ClientError::Config(format!("invalid indexer endpoint {}: {}", self.endpoint, e))
+        })?;
+        IndexerJsonRpcClient::connect(url)
+            .map_err(|e| ClientError::Indexer(format!("{}: {}", self.endpoint, e)))
+    }
+
+    /// The latest version of the substate at `address`.
+    pub async fn substate(&self, address: SubstateAddress) -> Result<SubstateValue, ClientError> {
+        Ok(self.substate_version(address, None).await?.value)
+    }
+
+    /// The substate at `address` as of `version`, or the latest version if none is given.
+    pub async fn substate_version(
+        &self,
+        address: SubstateAddress,
+        version: Option<u32>,
+    ) -> Result<VersionedSubstate, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .get_substate(GetSubstateRequest {
+                address: address.clone(),
+                version,
+                local_search_only: false,
+            })
+            .await
+            .map_err(|e| ClientError::Indexer(format!("could not get {}: {}", address, e)))?;
+        Ok(VersionedSubstate {
+            value: r.substate,
+            version: r.version,
+            created_by: r.created_by_transaction,
+        })
+    }
+
+    /// The substates a finalized transaction consumed and created. Transactions that were
+    /// rejected changed nothing.
+    pub async fn transaction_diff(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<SubstateDiff, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .get_transaction_result(GetTransactionResultRequest { transaction_id })
+            .await
+            .map_err(|e| {
+                ClientError::Indexer(format!(
+                    "could not get the result of transaction {}: {}",
+                    transaction_id, e
+                ))
+            })?;
+        match r.result {
+            IndexerTransactionFinalizedResult::Pending => Err(ClientError::Indexer(format!(
+                "transaction {} is not finalized yet",
+                transaction_id
+            ))),
+            IndexerTransactionFinalizedResult::Finalized {
+                execution_result, ..
+            } => Ok(execution_result
+                .and_then(|result| result.finalize.result.accept().cloned())
+                .unwrap_or_else(SubstateDiff::new)),
+        }
+    }
+
+    pub async fn component(
+        &self,
+        address: ComponentAddress,
+    ) -> Result<ComponentHeader, ClientError> {
+        match self.substate(SubstateAddress::Component(address)).await? {
+            SubstateValue::Component(component) => Ok(component),
+            _ => Err(ClientError::Indexer(format!(
+                "{} is not a component",
+                address
+            ))),
+        }
+    }
+
+    pub async fn vault(&self, vault_id: VaultId) -> Result<Vault, ClientError> {
+        match self.substate(SubstateAddress::Vault(vault_id)).await? {
+            SubstateValue::Vault(vault) => Ok(vault),
+            _ => Err(ClientError::Indexer(format!("{} is not a vault", vault_id))),
+        }
+    }
+
+    /// The ids of every vault referenced from the state of `component`.
+    pub async fn component_vault_ids(
+        &self,
+        address: ComponentAddress,
+    ) -> Result<Vec<VaultId>, ClientError> {
+        let component = self.component(address).await?;
+        let state = IndexedValue::from_raw(component.state()).map_err(|e| {
+            ClientError::Indexer(format!("could not decode the state of {}: {}", address, e))
+        })?;
+        Ok(state.vault_ids().to_vec())
+    }
+
+    /// Every vault referenced from the state of `component`, such as the vaults of an account.
+    pub async fn component_vaults(
+        &self,
+        address: ComponentAddress,
+    ) -> Result<Vec<(VaultId, Vault)>, ClientError> {
+        let mut vaults = vec![];
+        for vault_id in self.component_vault_ids(address).await? {
+            vaults.push((vault_id, self.vault(vault_id).await?));
+        }
+        Ok(vaults)
+    }
+
+    /// How many tokens of the non-fungible `resource` have been minted.
+    pub async fn non_fungible_count(&self, resource: ResourceAddress) -> Result<u64, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .get_non_fungible_count(GetNonFungibleCountRequest {
+                address: SubstateAddress::Resource(resource),
+            })
+            .await
+            .map_err(|e| ClientError::Indexer(format!("could not count {}: {}", resource, e)))?;
+        Ok(r.count)
+    }
+
+    /// The tokens of the non-fungible `resource` from `offset`, at most `limit` of them, in the
+    /// order they were minted. Burnt tokens have no contents.
+    pub async fn non_fungibles(
+        &self,
+        resource: ResourceAddress,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<(NonFungibleId, NonFungibleContainer)>, ClientError> {
+        let mut client = self.connect()?;
+        let r = client
+            .get_non_fungibles(GetNonFungiblesRequest {
+                address: SubstateAddress::Resource(resource),
+                start_index: offset,
+                end_index: offset + limit,
+            })
+            .await
+            .map_err(|e| {
+                ClientError::Indexer(format!("could not list tokens of {}: {}", resource, e))
+            })?;
+
+        Ok(r.non_fungibles
+            .into_iter()
+            .filter_map(|nft| {
+                let SubstateAddress::NonFungible(address) = nft.address else {
+                    return None;
+                };
+                match nft.substate.into_substate_value() {
+                    SubstateValue::NonFungible(container) => {
+                        Some((address.id().clone(), container))
+                    }
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,109 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! A local record of the compliance actions taken with this client. The network only shows
+//! the current state, e.g. which badges the coin component holds, not when or by whom it got
+//! there, so the commands that change the blacklist append an entry here.
+//!
+//! The journal is a file of JSON lines, one [`JournalEntry`] per line, and is only ever
+//! appended to.
+
+use crate::error::ClientError;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub const JOURNAL_FILE_NAME: &str = "journal.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalAction {
+    Blacklist,
+    RemoveFromBlacklist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub time: DateTime<Utc>,
+    pub action: JournalAction,
+    pub user_id: u64,
+    /// The vault the user's badge was recalled from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<String>,
+    /// The user's account, when it was blacklisted by account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// The account whose admin badge authorized the action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_account: Option<String>,
+    /// The wallet account that submitted the transaction
+    pub wallet_account: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// `journal.jsonl` in the user data directory, e.g. `~/.local/share/tari_stablecoin`.
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_default()
+            .join("tari_stablecoin")
+            .join(JOURNAL_FILE_NAME)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &JournalEntry) -> Result<(), ClientError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let line = serde_json::to_string(entry).expect("journal entries are serializable");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    /// Every entry, oldest first. A journal that does not exist yet is empty.
+    pub fn entries(&self) -> Result<Vec<JournalEntry>, ClientError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    ClientError::Config(format!(
+                        "line {} of the journal {} is invalid: {}",
+                        i + 1,
+                        self.path.display(),
+                        e
+                    ))
+                })
+            })
+            .collect()
+    }
+}
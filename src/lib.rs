@@ -0,0 +1,29 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Client library for the Tari stablecoin template. [`StablecoinClient`] builds and submits the
+//! template's transactions through a Tari wallet daemon; the `tari_stable_coin_example` binary
+//! is a command line interface over it.
+
+pub mod address;
+pub mod amount;
+pub mod audit;
+pub mod batch;
+pub mod client;
+pub mod config;
+pub mod daemon_client;
+pub mod error;
+pub mod history;
+pub mod indexer;
+pub mod journal;
+pub mod prepared;
+pub mod trace;
+pub mod users;
+
+pub use client::StablecoinClient;
+pub use client::UserData;
+pub use config::Deployment;
+pub use daemon_client::DaemonClient;
+pub use daemon_client::TransactionOutcome;
+pub use error::ClientError;
+pub use indexer::IndexerClient;
@@ -2,121 +2,147 @@
 //  SPDX-License-Identifier: BSD-3-Clause
 
 mod cli;
-mod daemon_client;
+mod output;
 
 use crate::cli::Cli;
 use crate::cli::Command;
-use crate::daemon_client::DaemonClient;
+use crate::output::CommandOutput;
+use tari_stable_coin_example::config::ConfigFile;
+use tari_stable_coin_example::config::DEFAULT_PROFILE;
+use tari_stable_coin_example::ClientError;
+use tari_stable_coin_example::DaemonClient;
+use tari_stable_coin_example::IndexerClient;
+use tari_stable_coin_example::StablecoinClient;
 
 use std::fs;
-use tari_utilities::hex::from_hex;
+use std::process;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::init();
-    let jrpc = cli
-        .daemon_jrpc_endpoint
-        .clone()
-        .unwrap_or_else(|| "http://127.0.0.1:18016".to_string());
+    let format = cli.output;
+    let command = cli.command.name();
+    let exit_code = output::report(format, command, run(cli).await);
+    process::exit(exit_code);
+}
+
+async fn run(cli: Cli) -> Result<CommandOutput, ClientError> {
+    let mut config = ConfigFile::load(cli.config.as_deref())?;
+    let profile_name = config
+        .profile_name(cli.profile.as_deref())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    let mut profile = config.profile(cli.profile.as_deref())?;
+    cli.apply_to(&mut profile);
+    let deployment = profile.deployment.clone();
+    let max_fee = profile.max_fee();
+
     let token = cli
         .auth_token
         .as_ref()
         .map(|a| a.to_string())
         .or(fs::read_to_string("token.data").ok());
 
-    let client = DaemonClient::new(jrpc, token, cli.default_account.clone());
-    let template_address = from_hex(&cli.template).unwrap().try_into().unwrap();
-    let cli_clone_hack = cli.clone();
-    match cli.command {
+    let wait_timeout = if cli.no_wait {
+        None
+    } else {
+        Some(Duration::from_secs(cli.wait_timeout))
+    };
+    let mut client = DaemonClient::new(profile.endpoint(), token, profile.account(), wait_timeout);
+    if let Some(path) = &cli.prepare {
+        client = client.with_prepare(path.clone());
+    }
+    let stablecoin = StablecoinClient::new(client.clone(), deployment.clone())
+        .with_max_fee(max_fee)
+        .with_dump_buckets(cli.dump_buckets)
+        .with_dry_run(cli.dry_run);
+    let indexer = IndexerClient::new(profile.indexer());
+    let mut command = cli.command;
+    command
+        .resolve_accounts(&config.config.aliases, &client)
+        .await?;
+    let output = match command {
         Command::Login(com) => {
-            com.run(client).await;
+            com.run(client).await?;
+            CommandOutput::default()
         }
 
-        Command::Instantiate(com) => {
-            com.run(client, template_address, cli.dump_buckets, cli.max_fee)
-                .await;
+        Command::PublishTemplate(com) => {
+            com.run(client, max_fee, &mut config, &profile_name).await?
         }
 
-        Command::IncreaseSupply(com) => {
+        Command::Instantiate(com) => {
             com.run(
                 client,
+                &deployment,
                 cli.dump_buckets,
-                cli.dry_run,
-                cli.max_fee,
-                cli_clone_hack,
+                max_fee,
+                &mut config,
+                &profile_name,
             )
-            .await;
+            .await?
         }
 
-        Command::DecreaseSupply(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
+        Command::Bootstrap(com) => {
+            com.run(client, &deployment, max_fee, &mut config, &profile_name)
+                .await?
         }
 
-        Command::TotalSupply(com) => {
-            com.run(
-                client,
-                cli.dump_buckets,
-                cli.dry_run,
-                cli.max_fee,
-                cli_clone_hack,
-            )
-            .await;
-        }
+        Command::IncreaseSupply(com) => com.run(stablecoin).await?.into(),
 
-        Command::Withdraw(com) => {
-            com.run(
-                client,
-                cli.dump_buckets,
-                cli.dry_run,
-                cli.max_fee,
-                cli_clone_hack,
-            )
-            .await;
-        }
+        Command::DecreaseSupply(com) => com.run(stablecoin).await?.into(),
 
-        Command::Deposit(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
-        }
+        Command::TotalSupply(com) => com.run(stablecoin).await?,
 
-        Command::CreateNewAdmin(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
-        }
+        Command::Balance(com) => com.run(client, &profile).await?,
+        Command::History(com) => com.run(client, &profile).await?,
 
-        Command::CreateNewUser(com) => {
-            com.run(
-                client,
-                cli.dump_buckets,
-                cli.dry_run,
-                cli.max_fee,
-                cli_clone_hack,
-            )
-            .await;
-        }
+        Command::Withdraw(com) => com.run(stablecoin).await?.into(),
 
-        Command::BlacklistUser(com) => {
-            com.run(client, cli.dry_run, cli.max_fee, cli_clone_hack)
-                .await;
-        }
-        Command::RemoveFromBlacklist(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
+        Command::MintAndDistribute(com) => com.run(stablecoin).await?.into(),
+
+        Command::Deposit(com) => com.run(stablecoin).await?.into(),
+
+        Command::CreateNewAdmin(com) => com.run(stablecoin).await?.into(),
+
+        Command::CreateNewUser(com) => com.run(stablecoin).await?.into(),
+
+        Command::BlacklistUser(com) => com.run(stablecoin, indexer, &profile.journal()).await?,
+
+        Command::RemoveFromBlacklist(com) => com.run(stablecoin, &profile.journal()).await?.into(),
+
+        Command::GetUserData(com) => com.run(stablecoin).await?,
+
+        Command::SetUserData(com) => com.run(stablecoin).await?.into(),
+
+        Command::Send(com) => com.run(stablecoin).await?.into(),
+
+        Command::BatchSend(com) => {
+            com.run(stablecoin, &config.config.aliases, cli.dry_run)
+                .await?
         }
 
-        Command::GetUserData(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
+        Command::SubmitPrepared(com) => com.run(client, &profile, cli.dry_run).await?,
+
+        Command::Users(com) => {
+            com.run(client, indexer, &profile, &config.config.aliases)
+                .await?
         }
 
-        Command::SetUserData(com) => {
-            com.run(client, cli.dump_buckets, cli.dry_run, cli.max_fee)
-                .await;
+        Command::Blacklist(com) => com.run(indexer, &profile).await?,
+
+        Command::Trace(com) => {
+            com.run(client, indexer, &profile, &config.config.aliases)
+                .await?
         }
-        Command::Send(com) => {
-            com.run(client, cli.dry_run, cli.max_fee, cli_clone_hack)
-                .await;
+
+        Command::Audit(com) => {
+            com.run(stablecoin, indexer, &profile, &config.config.aliases)
+                .await?
         }
-    }
+
+        Command::Alias(com) => com.run(&mut config)?,
+    };
+
+    Ok(output)
 }
@@ -0,0 +1,184 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use clap::ValueEnum;
+use serde::Serialize;
+use tari_stable_coin_example::ClientError;
+use tari_stable_coin_example::TransactionOutcome;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Everything a command produced that should be reported back to the caller.
+#[derive(Debug, Default)]
+pub struct CommandOutput {
+    pub transaction: Option<TransactionOutcome>,
+    /// Values decoded from the transaction result, as a JSON object
+    pub return_values: Option<serde_json::Value>,
+    /// A failure to report along with the output, for commands that still have results to show
+    pub error: Option<ClientError>,
+}
+
+impl CommandOutput {
+    pub fn with_return_values(mut self, return_values: serde_json::Value) -> Self {
+        self.return_values = Some(return_values);
+        self
+    }
+
+    pub fn with_error(mut self, error: ClientError) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+impl From<TransactionOutcome> for CommandOutput {
+    fn from(outcome: TransactionOutcome) -> Self {
+        Self {
+            transaction: Some(outcome),
+            return_values: None,
+            error: None,
+        }
+    }
+}
+
+/// The JSON document printed for every command in `--output json` mode, on success or failure.
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    command: &'a str,
+    transaction_id: Option<String>,
+    prepared_file: Option<String>,
+    status: Option<String>,
+    timed_out: bool,
+    fee: Option<i64>,
+    reject_reason: Option<String>,
+    return_values: Option<&'a serde_json::Value>,
+    error: Option<ErrorReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorReport {
+    kind: &'static str,
+    message: String,
+    exit_code: i32,
+}
+
+/// Prints the result of `command` in the requested format and returns the process exit code.
+pub fn report(
+    format: OutputFormat,
+    command: &str,
+    result: Result<CommandOutput, ClientError>,
+) -> i32 {
+    let (output, error) = match result {
+        Ok(mut output) => {
+            let error = output
+                .error
+                .take()
+                .or_else(|| output.transaction.as_ref().and_then(outcome_error));
+            (output, error)
+        }
+        Err(err) => (CommandOutput::default(), Some(err)),
+    };
+
+    match format {
+        OutputFormat::Text => print_text(&output, error.as_ref()),
+        OutputFormat::Json => print_json(command, &output, error.as_ref()),
+    }
+    error.map(|e| e.exit_code()).unwrap_or(0)
+}
+
+/// A transaction that was rejected, or is still pending when the wait for it ran out, fails
+/// the command.
+fn outcome_error(outcome: &TransactionOutcome) -> Option<ClientError> {
+    match outcome.reject_reason() {
+        Some(reason) => Some(ClientError::TransactionRejected(reason)),
+        None if outcome.timed_out => Some(ClientError::TimedOut(format!(
+            "transaction {} is still {}",
+            outcome.transaction_id_hex().unwrap_or_default(),
+            outcome.status_name()
+        ))),
+        None => None,
+    }
+}
+
+fn print_text(output: &CommandOutput, error: Option<&ClientError>) {
+    if let Some(outcome) = &output.transaction {
+        if let Some(transaction_id) = outcome.transaction_id_hex() {
+            println!("Transaction: {}", transaction_id);
+        }
+        if let Some(path) = &outcome.prepared {
+            println!("Prepared transaction written to {}", path.display());
+        }
+        if outcome.timed_out {
+            println!(
+                "Status: {} (timed out waiting for finalization)",
+                outcome.status_name()
+            );
+        } else {
+            println!("Status: {}", outcome.status_name());
+        }
+        if let Some(fee) = &outcome.final_fee {
+            println!("Fee paid: {}", fee);
+        }
+    }
+    if let Some(serde_json::Value::Object(values)) = &output.return_values {
+        for (name, value) in values {
+            match value {
+                serde_json::Value::String(value) => println!("{}: {}", name, value),
+                serde_json::Value::Array(items) => {
+                    println!("{}:", name);
+                    for item in items {
+                        println!("  {}", text_item(item));
+                    }
+                }
+                value => println!("{}: {}", name, value),
+            }
+        }
+    }
+    if let Some(err) = error {
+        eprintln!("Error: {}", err);
+    }
+}
+
+/// One element of a list in text output. Objects are printed as `key: value` pairs on a line.
+fn text_item(item: &serde_json::Value) -> String {
+    match item {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => format!("{}: {}", key, value),
+                value => format!("{}: {}", key, value),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        item => item.to_string(),
+    }
+}
+
+fn print_json(command: &str, output: &CommandOutput, error: Option<&ClientError>) {
+    let outcome = output.transaction.as_ref();
+    let report = Report {
+        command,
+        transaction_id: outcome.and_then(|t| t.transaction_id_hex()),
+        prepared_file: outcome
+            .and_then(|t| t.prepared.as_ref())
+            .map(|path| path.display().to_string()),
+        status: outcome.map(|t| t.status_name()),
+        timed_out: outcome.map(|t| t.timed_out).unwrap_or(false),
+        fee: outcome.and_then(|t| t.final_fee).map(|fee| fee.value()),
+        reject_reason: outcome.and_then(|t| t.reject_reason()),
+        return_values: output.return_values.as_ref(),
+        error: error.map(|err| ErrorReport {
+            kind: err.kind(),
+            message: err.to_string(),
+            exit_code: err.exit_code(),
+        }),
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("report is always serializable")
+    );
+}
@@ -0,0 +1,299 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+use crate::client::component_address;
+use crate::client::resource_address;
+use crate::config::Profile;
+use crate::error::ClientError;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tari_engine_types::instruction::Instruction;
+use tari_engine_types::substate::SubstateAddress;
+use tari_template_lib::args::Arg;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_utilities::hex::Hex;
+use tari_wallet_daemon_client::types::CallInstructionRequest;
+use tari_wallet_daemon_client::ComponentAddressOrName;
+
+/// Version of the prepared transaction file format
+pub const PREPARED_VERSION: u32 = 1;
+
+/// Methods of the stablecoin template that accounts do not have, so a call of one is a call
+/// of the coin component
+const COIN_METHODS: &[&str] = &[
+    "increase_supply",
+    "decrease_supply",
+    "total_supply",
+    "create_new_admin",
+    "create_new_user",
+    "blacklist_user",
+    "remove_from_blacklist",
+    "get_user_data",
+    "set_user_data",
+];
+
+/// A complete transaction request written by `--prepare`, to be submitted later, possibly from
+/// another machine, with `submit-prepared`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedTransaction {
+    pub version: u32,
+    /// When the transaction was prepared, in seconds since the Unix epoch
+    pub prepared_at: u64,
+    pub request: CallInstructionRequest,
+}
+
+impl PreparedTransaction {
+    pub fn new(request: CallInstructionRequest) -> Self {
+        Self {
+            version: PREPARED_VERSION,
+            prepared_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            request,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ClientError> {
+        let contents = fs::read_to_string(path)?;
+        let prepared: Self = serde_json::from_str(&contents).map_err(|e| {
+            ClientError::invalid_argument(
+                "file",
+                format!("{} is not a prepared transaction: {}", path.display(), e),
+            )
+        })?;
+        if prepared.version != PREPARED_VERSION {
+            return Err(ClientError::invalid_argument(
+                "file",
+                format!(
+                    "{} has format version {}, expected {}",
+                    path.display(),
+                    prepared.version,
+                    PREPARED_VERSION
+                ),
+            ));
+        }
+        Ok(prepared)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ClientError> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| {
+            ClientError::InvalidResponse(format!("could not serialize the transaction: {}", e))
+        })?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Checks that the transaction was prepared for the same fee account as `profile`, that it
+    /// may not cost more than the profile allows, and that any stablecoin method it calls is
+    /// called on the profile's coin component.
+    pub fn validate(&self, profile: &Profile) -> Result<(), ClientError> {
+        let fee_account = account_name(&self.request.fee_account);
+        if fee_account != profile.account() {
+            return Err(ClientError::Config(format!(
+                "the transaction pays fees from {}, but the profile uses {}",
+                fee_account,
+                profile.account()
+            )));
+        }
+        if self.request.max_fee > profile.max_fee() {
+            return Err(ClientError::Config(format!(
+                "the transaction allows a fee of up to {}, more than the profile's max_fee of {}",
+                self.request.max_fee,
+                profile.max_fee()
+            )));
+        }
+        // Transactions that do not call the coin component, such as publishing the template or
+        // instantiating it, may be submitted with any deployment
+        if let Some(coin_component) = &profile.deployment.coin_component {
+            let coin_component = component_address(coin_component)?;
+            let other_call = self
+                .coin_method_calls()
+                .into_iter()
+                .find(|(component, _)| *component != coin_component);
+            if let Some((component, method)) = other_call {
+                return Err(ClientError::Config(format!(
+                    "the transaction calls {} on {}, but the profile's coin component is {}",
+                    method, component, coin_component
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// A readable description of the transaction, one line per item. Addresses of the
+    /// profile's deployment are shown by name.
+    pub fn summary(&self, profile: &Profile) -> Vec<String> {
+        let names = Names::new(profile);
+        let request = &self.request;
+        let mut lines = vec![
+            format!("Fee account: {}", account_name(&request.fee_account)),
+            format!("Max fee: {}", request.max_fee),
+        ];
+        if let Some(account) = &request.dump_outputs_into {
+            lines.push(format!("Leftover buckets go to: {}", account_name(account)));
+        }
+        if request.is_dry_run {
+            lines.push("Dry run: the transaction will not be committed".to_string());
+        }
+        for input in &request.inputs {
+            lines.push(format!("Input: {}", names.substate(input.address())));
+        }
+        for (i, instruction) in request.instructions.iter().enumerate() {
+            lines.push(format!("{}. {}", i + 1, names.instruction(instruction)));
+        }
+        lines
+    }
+
+    /// The calls of methods only the stablecoin template has, with the component they are made on.
+    fn coin_method_calls(&self) -> Vec<(ComponentAddress, &str)> {
+        self.request
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::CallMethod {
+                    component_address,
+                    method,
+                    ..
+                } if COIN_METHODS.contains(&method.as_str()) => {
+                    Some((*component_address, method.as_str()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn account_name(account: &ComponentAddressOrName) -> String {
+    match account {
+        ComponentAddressOrName::Name(name) => name.clone(),
+        ComponentAddressOrName::ComponentAddress(address) => address.to_string(),
+    }
+}
+
+/// Names for the addresses of a deployment.
+struct Names {
+    components: Vec<(ComponentAddress, &'static str)>,
+    resources: Vec<(ResourceAddress, &'static str)>,
+}
+
+impl Names {
+    fn new(profile: &Profile) -> Self {
+        let deployment = &profile.deployment;
+        let components = [(deployment.coin_component.as_deref(), "coin_component")]
+            .into_iter()
+            .filter_map(|(address, name)| Some((component_address(address?).ok()?, name)))
+            .collect();
+        let resources = [
+            (deployment.coin_resource.as_deref(), "coin_resource"),
+            (
+                deployment.admin_badge_resource.as_deref(),
+                "admin_badge_resource",
+            ),
+            (
+                deployment.user_badge_resource.as_deref(),
+                "user_badge_resource",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(address, name)| Some((resource_address(address?).ok()?, name)))
+        .collect();
+        Self {
+            components,
+            resources,
+        }
+    }
+
+    fn component(&self, address: &ComponentAddress) -> String {
+        match self.components.iter().find(|(a, _)| a == address) {
+            Some((_, name)) => format!("{} ({})", name, address),
+            None => address.to_string(),
+        }
+    }
+
+    fn resource(&self, address: &ResourceAddress) -> String {
+        match self.resources.iter().find(|(a, _)| a == address) {
+            Some((_, name)) => format!("{} ({})", name, address),
+            None => address.to_string(),
+        }
+    }
+
+    fn substate(&self, address: &SubstateAddress) -> String {
+        match address {
+            SubstateAddress::Component(address) => self.component(address),
+            SubstateAddress::Resource(address) => self.resource(address),
+            address => address.to_string(),
+        }
+    }
+
+    fn instruction(&self, instruction: &Instruction) -> String {
+        match instruction {
+            Instruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } => format!(
+                "call {} on {}({})",
+                method,
+                self.component(component_address),
+                self.args(args)
+            ),
+            Instruction::CallFunction {
+                template_address,
+                function,
+                args,
+            } => format!(
+                "call function {} of template {}({})",
+                function,
+                template_address,
+                self.args(args)
+            ),
+            Instruction::PutLastInstructionOutputOnWorkspace { key } => {
+                format!("store the result as '{}'", String::from_utf8_lossy(key))
+            }
+            Instruction::DropAllProofsInWorkspace => "drop all proofs".to_string(),
+            Instruction::PublishTemplate { binary } => {
+                format!("publish a template of {} bytes", binary.len())
+            }
+            other => format!("{:?}", other),
+        }
+    }
+
+    fn args(&self, args: &[Arg]) -> String {
+        args.iter()
+            .map(|arg| self.arg(arg))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Literal arguments are shown as the first of the types the stablecoin commands use that
+    /// they decode as.
+    fn arg(&self, arg: &Arg) -> String {
+        let bytes = match arg {
+            Arg::Workspace(key) => return format!("'{}'", String::from_utf8_lossy(key)),
+            Arg::Literal(bytes) => bytes,
+        };
+        if let Ok(value) = tari_bor::decode::<u64>(bytes) {
+            return value.to_string();
+        }
+        if let Ok(value) = tari_bor::decode::<String>(bytes) {
+            return format!("{:?}", value);
+        }
+        if let Ok(address) = tari_bor::decode::<ResourceAddress>(bytes) {
+            return self.resource(&address);
+        }
+        if let Ok(address) = tari_bor::decode::<ComponentAddress>(bytes) {
+            return self.component(&address);
+        }
+        if let Ok(vault) = tari_bor::decode::<VaultId>(bytes) {
+            return vault.to_string();
+        }
+        format!("0x{}", bytes.to_hex())
+    }
+}
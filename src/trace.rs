@@ -0,0 +1,485 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Follows coins from transaction to transaction through the versions of the vaults they pass
+//! through, as recorded by the indexer.
+//!
+//! A transaction's coin movements are the changes it made to the balances of `coin_resource`
+//! vaults. Going backward from a withdrawal, the coins came from the deposits into that vault
+//! before it, most recent first, until they add up to the amount withdrawn. Going forward from
+//! a deposit, the coins went to the next withdrawal from that vault. Coins in a vault are not
+//! told apart, so this is how far the network lets them be followed.
+
+use crate::error::ClientError;
+use crate::indexer::IndexerClient;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use tari_engine_types::substate::SubstateAddress;
+use tari_engine_types::substate::SubstateValue;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+use tari_utilities::hex::Hex;
+
+/// How many versions of a vault to look through for the deposits or withdrawal connected to a
+/// movement before giving up.
+pub const DEFAULT_MAX_VERSIONS: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Where the coins went
+    Forward,
+    /// Where the coins came from
+    Backward,
+    Both,
+}
+
+impl Direction {
+    fn forward(self) -> bool {
+        matches!(self, Direction::Forward | Direction::Both)
+    }
+
+    fn backward(self) -> bool {
+        matches!(self, Direction::Backward | Direction::Both)
+    }
+}
+
+/// A change a transaction made to the balance of a coin vault.
+#[derive(Debug, Clone)]
+pub struct VaultChange {
+    pub vault: VaultId,
+    /// The version of the vault the transaction created
+    pub version: u32,
+    /// The version the transaction consumed, None if it created the vault
+    pub previous_version: Option<u32>,
+    /// Positive for a deposit, negative for a withdrawal
+    pub change: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TracedTransaction {
+    pub transaction_id: TransactionId,
+    /// How many transactions away from a starting transaction this one is
+    pub depth: u32,
+    pub changes: Vec<VaultChange>,
+}
+
+/// Coins deposited into `vault` by `from` and withdrawn from it by `to`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Link {
+    pub from: TransactionId,
+    pub to: TransactionId,
+    pub vault: VaultId,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub roots: Vec<TransactionId>,
+    /// Every transaction reached, in the order they were reached
+    pub transactions: Vec<TracedTransaction>,
+    /// Every link found, once each, ordered by `from`, `to` and `vault`
+    pub links: Vec<Link>,
+}
+
+pub struct Tracer<'a> {
+    indexer: &'a IndexerClient,
+    coin_resource: ResourceAddress,
+    max_versions: u32,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(indexer: &'a IndexerClient, coin_resource: ResourceAddress) -> Self {
+        Self {
+            indexer,
+            coin_resource,
+            max_versions: DEFAULT_MAX_VERSIONS,
+        }
+    }
+
+    pub fn with_max_versions(mut self, max_versions: u32) -> Self {
+        self.max_versions = max_versions;
+        self
+    }
+
+    /// Follows the coins moved by `roots` for up to `depth` transactions in `direction`.
+    pub async fn trace(
+        &self,
+        roots: Vec<TransactionId>,
+        depth: u32,
+        direction: Direction,
+    ) -> Result<Trace, ClientError> {
+        let mut trace = Trace {
+            roots: roots.clone(),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let mut links = vec![];
+        let mut queue: VecDeque<_> = roots.into_iter().map(|id| (id, 0, direction)).collect();
+
+        while let Some((transaction_id, tx_depth, direction)) = queue.pop_front() {
+            if !seen.insert(transaction_id) {
+                continue;
+            }
+            let changes = self.changes(transaction_id).await?;
+
+            if tx_depth < depth {
+                for change in &changes {
+                    if change.change < 0 && direction.backward() {
+                        let Some(previous_version) = change.previous_version else {
+                            continue;
+                        };
+                        let deposits = self
+                            .funding_deposits(change.vault, previous_version, -change.change)
+                            .await?;
+                        for deposit in deposits {
+                            add_link(
+                                &mut links,
+                                Link {
+                                    from: deposit,
+                                    to: transaction_id,
+                                    vault: change.vault,
+                                },
+                            );
+                            queue.push_back((deposit, tx_depth + 1, Direction::Backward));
+                        }
+                    }
+                    if change.change > 0 && direction.forward() {
+                        if let Some(withdrawal) =
+                            self.next_withdrawal(change.vault, change.version).await?
+                        {
+                            add_link(
+                                &mut links,
+                                Link {
+                                    from: transaction_id,
+                                    to: withdrawal,
+                                    vault: change.vault,
+                                },
+                            );
+                            queue.push_back((withdrawal, tx_depth + 1, Direction::Forward));
+                        }
+                    }
+                }
+            }
+
+            trace.transactions.push(TracedTransaction {
+                transaction_id,
+                depth: tx_depth,
+                changes,
+            });
+        }
+
+        // Sorted so the tree and graph come out the same on every run
+        links.sort_by_key(|link| {
+            (
+                link.from.as_bytes().to_vec(),
+                link.to.as_bytes().to_vec(),
+                link.vault.to_string(),
+            )
+        });
+        trace.links = links;
+        Ok(trace)
+    }
+
+    /// The transactions that made the last `limit` changes to `vault`, latest first.
+    pub async fn recent_transactions(
+        &self,
+        vault: VaultId,
+        limit: u32,
+    ) -> Result<Vec<TransactionId>, ClientError> {
+        let latest = self
+            .indexer
+            .substate_version(SubstateAddress::Vault(vault), None)
+            .await?;
+        let first = latest.version.saturating_sub(limit.saturating_sub(1));
+        let mut transactions = vec![latest.created_by];
+        for version in (first..latest.version).rev() {
+            let (_, created_by) = self.balance_at(vault, version).await?;
+            if !transactions.contains(&created_by) {
+                transactions.push(created_by);
+            }
+        }
+        Ok(transactions)
+    }
+
+    /// The changes `transaction_id` made to coin vaults.
+    pub async fn changes(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Result<Vec<VaultChange>, ClientError> {
+        let diff = self.indexer.transaction_diff(transaction_id).await?;
+        let consumed: HashMap<_, _> = diff
+            .down_iter()
+            .map(|(address, version)| (address.clone(), *version))
+            .collect();
+
+        let mut changes = vec![];
+        for (address, substate) in diff.up_iter() {
+            let (SubstateAddress::Vault(vault_id), SubstateValue::Vault(vault)) =
+                (address, substate.substate_value())
+            else {
+                continue;
+            };
+            if *vault.resource_address() != self.coin_resource {
+                continue;
+            }
+            let previous_version = consumed.get(address).copied();
+            let previous_balance = match previous_version {
+                Some(version) => self.balance_at(*vault_id, version).await?.0,
+                None => 0,
+            };
+            let change = vault.balance().value() - previous_balance;
+            if change != 0 {
+                changes.push(VaultChange {
+                    vault: *vault_id,
+                    version: substate.version(),
+                    previous_version,
+                    change,
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// The balance of `vault` as of `version` and the transaction that created that version.
+    async fn balance_at(
+        &self,
+        vault: VaultId,
+        version: u32,
+    ) -> Result<(i64, TransactionId), ClientError> {
+        let substate = self
+            .indexer
+            .substate_version(SubstateAddress::Vault(vault), Some(version))
+            .await?;
+        match substate.value {
+            SubstateValue::Vault(v) => Ok((v.balance().value(), substate.created_by)),
+            _ => Err(ClientError::Indexer(format!("{} is not a vault", vault))),
+        }
+    }
+
+    /// The first transaction after `version` of `vault` that withdrew from it.
+    async fn next_withdrawal(
+        &self,
+        vault: VaultId,
+        version: u32,
+    ) -> Result<Option<TransactionId>, ClientError> {
+        let latest = self
+            .indexer
+            .substate_version(SubstateAddress::Vault(vault), None)
+            .await?
+            .version;
+        let last = latest.min(version.saturating_add(self.max_versions));
+        let (mut previous_balance, _) = self.balance_at(vault, version).await?;
+        for version in version + 1..=last {
+            let (balance, created_by) = self.balance_at(vault, version).await?;
+            if balance < previous_balance {
+                return Ok(Some(created_by));
+            }
+            previous_balance = balance;
+        }
+        Ok(None)
+    }
+
+    /// The deposits into `vault` up to and including `version`, latest first, that together
+    /// hold at least `amount`.
+    async fn funding_deposits(
+        &self,
+        vault: VaultId,
+        version: u32,
+        amount: i64,
+    ) -> Result<Vec<TransactionId>, ClientError> {
+        let first = version.saturating_sub(self.max_versions);
+        let mut deposits = vec![];
+        let mut funded = 0;
+        let mut current = self.balance_at(vault, version).await?;
+        for version in (first..=version).rev() {
+            let previous = match version {
+                0 => None,
+                version => Some(self.balance_at(vault, version - 1).await?),
+            };
+            let (balance, created_by) = current;
+            if balance > previous.map_or(0, |(balance, _)| balance) {
+                deposits.push(created_by);
+                funded += balance - previous.map_or(0, |(balance, _)| balance);
+                if funded >= amount {
+                    break;
+                }
+            }
+            match previous {
+                Some(previous) => current = previous,
+                None => break,
+            }
+        }
+        Ok(deposits)
+    }
+}
+
+impl Trace {
+    fn transaction(&self, transaction_id: &TransactionId) -> Option<&TracedTransaction> {
+        self.transactions
+            .iter()
+            .find(|tx| tx.transaction_id == *transaction_id)
+    }
+
+    /// The trace as an indented tree, one line per item, starting from each root. Vaults are
+    /// named by `vault_label` and amounts formatted by `amount`.
+    pub fn tree<V, A>(&self, vault_label: V, amount: A) -> Vec<String>
+    where
+        V: Fn(&VaultId) -> String,
+        A: Fn(i64) -> String,
+    {
+        let mut tree = Tree {
+            trace: self,
+            vault_label,
+            amount,
+            shown: HashSet::new(),
+            lines: vec![],
+        };
+        for root in &self.roots {
+            tree.node(root, "", 0, Direction::Both);
+        }
+        tree.lines
+    }
+
+    /// The trace as a graph of transactions and vaults: withdrawals are edges from a vault to a
+    /// transaction and deposits edges from a transaction to a vault.
+    pub fn graph<V, A>(&self, vault_label: V, amount: A) -> serde_json::Value
+    where
+        V: Fn(&VaultId) -> String,
+        A: Fn(i64) -> String,
+    {
+        let mut nodes = vec![];
+        let mut vaults = HashSet::new();
+        let mut edges = vec![];
+        for tx in &self.transactions {
+            let tx_node = transaction_hex(&tx.transaction_id);
+            nodes.push(serde_json::json!({
+                "id": tx_node,
+                "kind": "transaction",
+                "label": tx_node,
+                "depth": tx.depth,
+            }));
+            for change in &tx.changes {
+                let vault_node = change.vault.to_string();
+                if vaults.insert(change.vault) {
+                    nodes.push(serde_json::json!({
+                        "id": vault_node,
+                        "kind": "vault",
+                        "label": vault_label(&change.vault),
+                    }));
+                }
+                let (from, to) = if change.change < 0 {
+                    (vault_node, tx_node.clone())
+                } else {
+                    (tx_node.clone(), vault_node)
+                };
+                edges.push(serde_json::json!({
+                    "from": from,
+                    "to": to,
+                    "amount": amount(change.change.abs()),
+                }));
+            }
+        }
+        serde_json::json!({ "nodes": nodes, "edges": edges })
+    }
+
+    /// The graph of [`Trace::graph`] in Graphviz DOT.
+    pub fn dot<V, A>(&self, vault_label: V, amount: A) -> String
+    where
+        V: Fn(&VaultId) -> String,
+        A: Fn(i64) -> String,
+    {
+        let graph = self.graph(vault_label, amount);
+        let mut dot = String::from("digraph trace {\n  rankdir=LR;\n");
+        for node in graph["nodes"].as_array().into_iter().flatten() {
+            let shape = match node["kind"].as_str() {
+                Some("transaction") => "box",
+                _ => "ellipse",
+            };
+            dot.push_str(&format!(
+                "  {} [shape={}, label={}];\n",
+                node["id"], shape, node["label"]
+            ));
+        }
+        for edge in graph["edges"].as_array().into_iter().flatten() {
+            dot.push_str(&format!(
+                "  {} -> {} [label={}];\n",
+                edge["from"], edge["to"], edge["amount"]
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A link can be found from both of its transactions, but is only kept once.
+fn add_link(links: &mut Vec<Link>, link: Link) {
+    if !links.contains(&link) {
+        links.push(link);
+    }
+}
+
+struct Tree<'a, V, A> {
+    trace: &'a Trace,
+    vault_label: V,
+    amount: A,
+    shown: HashSet<TransactionId>,
+    lines: Vec<String>,
+}
+
+impl<'a, V, A> Tree<'a, V, A>
+where
+    V: Fn(&VaultId) -> String,
+    A: Fn(i64) -> String,
+{
+    /// Adds `transaction_id` and the transactions linked to it in `direction`. A transaction
+    /// reached again is only named.
+    fn node(
+        &mut self,
+        transaction_id: &TransactionId,
+        prefix: &str,
+        indent: usize,
+        direction: Direction,
+    ) {
+        let pad = "  ".repeat(indent);
+        let hex = transaction_hex(transaction_id);
+        if !self.shown.insert(*transaction_id) {
+            self.lines
+                .push(format!("{}{}transaction {} (see above)", pad, prefix, hex));
+            return;
+        }
+        self.lines
+            .push(format!("{}{}transaction {}", pad, prefix, hex));
+        let Some(tx) = self.trace.transaction(transaction_id) else {
+            return;
+        };
+        for change in &tx.changes {
+            let sign = if change.change > 0 { "+" } else { "" };
+            self.lines.push(format!(
+                "{}  {}{} {}",
+                pad,
+                sign,
+                (self.amount)(change.change),
+                (self.vault_label)(&change.vault)
+            ));
+        }
+
+        let trace = self.trace;
+        if direction.backward() {
+            for link in trace.links.iter().filter(|l| l.to == *transaction_id) {
+                let prefix = format!("funded via {} by ", (self.vault_label)(&link.vault));
+                self.node(&link.from, &prefix, indent + 1, Direction::Backward);
+            }
+        }
+        if direction.forward() {
+            for link in trace.links.iter().filter(|l| l.from == *transaction_id) {
+                let prefix = format!("spent from {} in ", (self.vault_label)(&link.vault));
+                self.node(&link.to, &prefix, indent + 1, Direction::Forward);
+            }
+        }
+    }
+}
+
+pub fn transaction_hex(transaction_id: &TransactionId) -> String {
+    transaction_id.as_bytes().to_vec().to_hex()
+}
@@ -0,0 +1,202 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! The users registered with `create_new_user`, read from the user badges on the network.
+//!
+//! Holders are found by looking through the vaults of the accounts we know about (see
+//! [`IndexerClient`]). A badge the coin component holds has been recalled by
+//! `blacklist_user`.
+
+use crate::client::component_address;
+use crate::client::resource_address;
+use crate::config::Deployment;
+use crate::error::ClientError;
+use crate::indexer::IndexerClient;
+use std::collections::BTreeMap;
+use tari_engine_types::non_fungible::NonFungibleContainer;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+
+/// The vault a badge is in and the component that owns the vault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadgeHolder {
+    pub component: ComponentAddress,
+    pub vault: VaultId,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserBadge {
+    pub id: NonFungibleId,
+    /// None if the badge is not in any of the components that were searched
+    pub holder: Option<BadgeHolder>,
+    pub blacklisted: bool,
+    /// The badge's mutable data, which holds what `set_user_data` stored
+    pub data: serde_json::Value,
+    pub burnt: bool,
+}
+
+impl UserBadge {
+    /// The `user_id` the badge was minted for.
+    pub fn user_id(&self) -> Option<u64> {
+        user_id(&self.id)
+    }
+}
+
+pub fn user_id(id: &NonFungibleId) -> Option<u64> {
+    match id {
+        NonFungibleId::Uint64(user_id) => Some(*user_id),
+        _ => None,
+    }
+}
+
+/// Finds the tokens of `resource` held by any of `components`, and where they are.
+pub async fn badge_holders(
+    indexer: &IndexerClient,
+    resource: ResourceAddress,
+    components: &[ComponentAddress],
+) -> Result<BTreeMap<NonFungibleId, BadgeHolder>, ClientError> {
+    let mut holders = BTreeMap::new();
+    for component in components {
+        for (vault_id, vault) in indexer.component_vaults(*component).await? {
+            if *vault.resource_address() != resource {
+                continue;
+            }
+            for id in vault.get_non_fungible_ids() {
+                holders.insert(
+                    id.clone(),
+                    BadgeHolder {
+                        component: *component,
+                        vault: vault_id,
+                    },
+                );
+            }
+        }
+    }
+    Ok(holders)
+}
+
+/// The user badge an account holds, and the vaults to name when blacklisting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountBadge {
+    pub user_id: u64,
+    /// The vault the badge is in, which `blacklist_user` recalls it from
+    pub badge_vault: VaultId,
+    /// The vault of the account holding its coins, if it has one
+    pub coin_vault: Option<VaultId>,
+}
+
+/// Finds the user badge in `account` and its coin vault. The account must hold exactly one
+/// user badge.
+pub async fn account_badge(
+    indexer: &IndexerClient,
+    deployment: &Deployment,
+    account: ComponentAddress,
+) -> Result<AccountBadge, ClientError> {
+    let user_badge_resource = resource_address(deployment.user_badge_resource()?)?;
+    let coin_resource = resource_address(deployment.coin_resource()?)?;
+
+    let mut badges = vec![];
+    let mut coin_vault = None;
+    for (vault_id, vault) in indexer.component_vaults(account).await? {
+        if *vault.resource_address() == user_badge_resource {
+            badges.extend(
+                vault
+                    .get_non_fungible_ids()
+                    .iter()
+                    .map(|id| (id.clone(), vault_id)),
+            );
+        } else if *vault.resource_address() == coin_resource {
+            coin_vault = Some(vault_id);
+        }
+    }
+
+    let (id, badge_vault) = match &badges[..] {
+        [badge] => badge.clone(),
+        [] => {
+            return Err(ClientError::invalid_argument(
+                "account",
+                format!("{} holds no user badge", account),
+            ))
+        }
+        badges => {
+            let ids: Vec<_> = badges.iter().map(|(id, _)| id.to_string()).collect();
+            return Err(ClientError::invalid_argument(
+                "account",
+                format!(
+                    "{} holds several user badges ({}), give the vault and user id instead",
+                    account,
+                    ids.join(", ")
+                ),
+            ));
+        }
+    };
+    let user_id = user_id(&id).ok_or_else(|| {
+        ClientError::invalid_argument(
+            "account",
+            format!(
+                "the user badge in {} has id {}, which is not a user id",
+                account, id
+            ),
+        )
+    })?;
+    Ok(AccountBadge {
+        user_id,
+        badge_vault,
+        coin_vault,
+    })
+}
+
+/// The badges the coin component holds, i.e. the users that are blacklisted.
+pub async fn blacklisted_users(
+    indexer: &IndexerClient,
+    deployment: &Deployment,
+) -> Result<BTreeMap<NonFungibleId, BadgeHolder>, ClientError> {
+    let coin_component = component_address(deployment.coin_component()?)?;
+    let user_badge_resource = resource_address(deployment.user_badge_resource()?)?;
+    badge_holders(indexer, user_badge_resource, &[coin_component]).await
+}
+
+/// The user badges of `deployment` from `offset`, at most `limit` of them. Holders are looked
+/// for in `accounts` and the coin component.
+pub async fn list_users(
+    indexer: &IndexerClient,
+    deployment: &Deployment,
+    accounts: &[ComponentAddress],
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<UserBadge>, ClientError> {
+    let coin_component = component_address(deployment.coin_component()?)?;
+    let user_badge_resource = resource_address(deployment.user_badge_resource()?)?;
+
+    let mut components = accounts.to_vec();
+    if !components.contains(&coin_component) {
+        components.push(coin_component);
+    }
+    let holders = badge_holders(indexer, user_badge_resource, &components).await?;
+
+    let badges = indexer
+        .non_fungibles(user_badge_resource, offset, limit)
+        .await?;
+    Ok(badges
+        .into_iter()
+        .map(|(id, container)| {
+            let holder = holders.get(&id).copied();
+            UserBadge {
+                blacklisted: holder.map_or(false, |h| h.component == coin_component),
+                data: mutable_data(&container),
+                burnt: container.contents().is_none(),
+                holder,
+                id,
+            }
+        })
+        .collect())
+}
+
+fn mutable_data(container: &NonFungibleContainer) -> serde_json::Value {
+    container
+        .contents()
+        .and_then(|nft| serde_json::to_value(nft.mutable_data()).ok())
+        .unwrap_or(serde_json::Value::Null)
+}
@@ -0,0 +1,216 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Checks how a supply audit adds up the coin vaults it found, and how it finds them on a mock
+//! indexer.
+
+mod common;
+
+use chrono::Utc;
+use common::component;
+use common::hex_address;
+use common::resource;
+use common::vault;
+use common::MockIndexer;
+use tari_stable_coin_example::audit::audit_supply;
+use tari_stable_coin_example::audit::CoinVault;
+use tari_stable_coin_example::audit::SupplyAudit;
+use tari_stable_coin_example::config::Deployment;
+use tari_stable_coin_example::journal::JournalAction;
+use tari_stable_coin_example::journal::JournalEntry;
+use tari_stable_coin_example::IndexerClient;
+use tari_template_lib::models::Amount;
+use tari_template_lib::prelude::NonFungibleId;
+
+fn coin_vault(owner: u8, id: u8, balance: i64, blacklisted: bool) -> CoinVault {
+    CoinVault {
+        component: component(owner),
+        vault: vault(id),
+        version: 3,
+        balance,
+        blacklisted,
+    }
+}
+
+/// A treasury of 500, alice with 300 over two vaults, bob with 150 and a blacklisted account
+/// with 40.
+fn audit(total_supply: i64) -> SupplyAudit {
+    SupplyAudit {
+        total_supply,
+        treasury: vec![coin_vault(0x01, 0xb0, 500, false)],
+        holdings: vec![
+            coin_vault(0xa2, 0xb1, 200, false),
+            coin_vault(0xa2, 0xb2, 100, false),
+            coin_vault(0xa3, 0xb3, 150, false),
+            coin_vault(0xa4, 0xb4, 40, true),
+            coin_vault(0xa5, 0xb5, 0, false),
+        ],
+        unresolved_blacklisted: vec![],
+        components_searched: 5,
+    }
+}
+
+#[test]
+fn supply_reconciles_when_every_coin_is_found() {
+    let audit = audit(990);
+    assert_eq!(audit.treasury_balance(), 500);
+    assert_eq!(audit.holdings_balance(), 490);
+    assert_eq!(audit.accounted(), 990);
+    assert_eq!(audit.discrepancy(), 0);
+}
+
+#[test]
+fn coins_that_were_not_found_are_a_discrepancy() {
+    assert_eq!(audit(1000).discrepancy(), 10);
+    assert_eq!(audit(980).discrepancy(), -10);
+}
+
+#[test]
+fn blacklisted_balance_counts_only_blacklisted_accounts() {
+    assert_eq!(audit(990).blacklisted_balance(), 40);
+}
+
+#[test]
+fn top_holders_are_summed_per_account_largest_first() {
+    let audit = audit(990);
+    assert_eq!(
+        audit.top_holders(10),
+        [
+            (component(0xa2), 300),
+            (component(0xa3), 150),
+            (component(0xa4), 40)
+        ],
+        "empty accounts and the treasury are left out"
+    );
+    assert_eq!(audit.top_holders(1), [(component(0xa2), 300)]);
+}
+
+fn deployment() -> Deployment {
+    Deployment {
+        template: Some(hex_address(0x00)),
+        coin_component: Some(component(0x01).to_string()),
+        admin_badge_resource: Some(resource(0x02).to_string()),
+        user_badge_resource: Some(resource(0x03).to_string()),
+        coin_resource: Some(resource(0x04).to_string()),
+    }
+}
+
+/// A treasury of 500, alice with 300 and a user badge, and bob with 150. The coin component
+/// has recalled the badges of users 2, 3 and 4.
+async fn network() -> MockIndexer {
+    let indexer = MockIndexer::start().await;
+    let (coin_resource, user_badge_resource) = (resource(0x04), resource(0x03));
+    indexer.add_fungible_vault(component(0x01), vault(0xc0), coin_resource, 500);
+    indexer.add_non_fungible_vault(
+        component(0x01),
+        vault(0xb0),
+        user_badge_resource,
+        &[2, 3, 4],
+    );
+    indexer.add_fungible_vault(component(0xa2), vault(0xc2), coin_resource, 300);
+    indexer.add_non_fungible_vault(component(0xa2), vault(0xb2), user_badge_resource, &[1]);
+    indexer.add_fungible_vault(component(0xa3), vault(0xc3), coin_resource, 150);
+    // Recalled from an account that is in no address book
+    indexer.add_non_fungible_vault(component(0xa4), vault(0xb4), user_badge_resource, &[]);
+    indexer.add_fungible_vault(component(0xa4), vault(0xc4), coin_resource, 40);
+    indexer
+}
+
+fn blacklisting(user_id: u64, vault: Option<String>, account: Option<String>) -> JournalEntry {
+    JournalEntry {
+        time: Utc::now(),
+        action: JournalAction::Blacklist,
+        user_id,
+        vault,
+        account,
+        admin_account: None,
+        wallet_account: "TestAccount_0".to_string(),
+        transaction_id: None,
+    }
+}
+
+async fn run_audit(
+    indexer: &MockIndexer,
+    total_supply: i64,
+    journal: &[JournalEntry],
+) -> SupplyAudit {
+    audit_supply(
+        &IndexerClient::new(indexer.endpoint().to_string()),
+        &deployment(),
+        Amount::new(total_supply),
+        &[component(0xa2), component(0xa3)],
+        journal,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_separates_the_treasury_from_the_holdings() {
+    let indexer = network().await;
+    let audit = run_audit(&indexer, 950, &[]).await;
+
+    assert_eq!(audit.treasury, [coin_vault_at(0x01, 0xc0, 500)]);
+    assert_eq!(
+        audit.holdings,
+        [
+            coin_vault_at(0xa2, 0xc2, 300),
+            coin_vault_at(0xa3, 0xc3, 150)
+        ],
+        "badge vaults are not coin vaults"
+    );
+    assert_eq!(audit.components_searched, 3);
+    assert_eq!(audit.discrepancy(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn coins_held_outside_the_searched_accounts_are_a_discrepancy() {
+    let indexer = network().await;
+    let audit = run_audit(&indexer, 990, &[]).await;
+
+    assert_eq!(audit.accounted(), 950);
+    assert_eq!(audit.discrepancy(), 40);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklisted_users_are_found_through_the_journal() {
+    let indexer = network().await;
+    let journal = [
+        blacklisting(2, Some(vault(0xb2).to_string()), None),
+        // Blacklisted by account, so the account is searched although it is not known otherwise
+        blacklisting(3, None, Some(component(0xa4).to_string())),
+    ];
+    let audit = run_audit(&indexer, 990, &journal).await;
+
+    let blacklisted: Vec<_> = audit
+        .holdings
+        .iter()
+        .filter(|v| v.blacklisted)
+        .map(|v| v.component)
+        .collect();
+    assert_eq!(blacklisted, [component(0xa2), component(0xa4)]);
+    assert_eq!(audit.blacklisted_balance(), 340);
+    assert_eq!(audit.components_searched, 4);
+    assert_eq!(audit.discrepancy(), 0);
+    assert_eq!(audit.unresolved_blacklisted, [NonFungibleId::from_u64(4)]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklisted_users_without_a_journal_entry_are_unresolved() {
+    let indexer = network().await;
+    let audit = run_audit(&indexer, 950, &[]).await;
+
+    assert_eq!(audit.blacklisted_balance(), 0);
+    assert_eq!(
+        audit.unresolved_blacklisted,
+        [2, 3, 4].map(NonFungibleId::from_u64)
+    );
+}
+
+/// A coin vault as the mock indexer reports it, at version 0.
+fn coin_vault_at(owner: u8, id: u8, balance: i64) -> CoinVault {
+    CoinVault {
+        version: 0,
+        ..coin_vault(owner, id, balance, false)
+    }
+}
@@ -0,0 +1,1162 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Runs the command line client against the mock wallet daemon.
+
+mod common;
+
+use common::component;
+use common::hex_address;
+use common::resource;
+use common::vault;
+use common::MockDaemon;
+use common::MockIndexer;
+use common::Scripted;
+use common::AUTH_TOKEN;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use tari_engine_types::instruction::Instruction;
+use tari_engine_types::substate::SubstateAddress;
+use tari_stable_coin_example::batch::BatchState;
+use tari_stable_coin_example::batch::PaymentStatus;
+use tari_stable_coin_example::config::Deployment;
+use tari_stable_coin_example::journal::Journal;
+use tari_stable_coin_example::journal::JournalAction;
+use tari_stable_coin_example::trace::transaction_hex;
+use tari_stable_coin_example::DaemonClient;
+use tari_stable_coin_example::StablecoinClient;
+use tari_template_lib::models::Amount;
+use tari_template_lib::models::NonFungibleAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_wallet_daemon_client::ComponentAddressOrName;
+
+const ACCOUNT: &str = "TestAccount_0";
+const MAX_FEE: u64 = 1500;
+
+fn deployment() -> Deployment {
+    Deployment {
+        template: Some(hex_address(0x00)),
+        coin_component: Some(component(0x01).to_string()),
+        admin_badge_resource: Some(resource(0x02).to_string()),
+        user_badge_resource: Some(resource(0x03).to_string()),
+        coin_resource: Some(resource(0x04).to_string()),
+    }
+}
+
+fn admin() -> String {
+    component(0xa1).to_string()
+}
+
+fn alice() -> String {
+    component(0xa2).to_string()
+}
+
+fn bob() -> String {
+    component(0xa3).to_string()
+}
+
+/// Writes a config file whose only profile holds the test deployment, with alice and bob in the
+/// address book.
+fn write_config() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "stablecoin-test-{}-{}.toml",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst)
+    ));
+    let deployment = deployment();
+    let config = format!(
+        concat!(
+            "default_profile = \"test\"\n",
+            "\n",
+            "[profiles.test]\n",
+            "account = \"{}\"\n",
+            "max_fee = {}\n",
+            "journal = {:?}\n",
+            "template = \"{}\"\n",
+            "coin_component = \"{}\"\n",
+            "admin_badge_resource = \"{}\"\n",
+            "user_badge_resource = \"{}\"\n",
+            "coin_resource = \"{}\"\n",
+            "\n",
+            "[aliases]\n",
+            "alice = \"{}\"\n",
+            "bob = \"{}\"\n",
+        ),
+        ACCOUNT,
+        MAX_FEE,
+        path.with_extension("jsonl"),
+        deployment.template.unwrap(),
+        deployment.coin_component.unwrap(),
+        deployment.admin_badge_resource.unwrap(),
+        deployment.user_badge_resource.unwrap(),
+        deployment.coin_resource.unwrap(),
+        alice(),
+        bob(),
+    );
+    std::fs::write(&path, config).unwrap();
+    path
+}
+
+/// Runs the client with `args` against `daemon` and returns its exit code and JSON report.
+async fn stablecoin(daemon: &MockDaemon, args: &[&str]) -> (i32, Value) {
+    stablecoin_with_token(daemon, AUTH_TOKEN, args).await
+}
+
+async fn stablecoin_with_token(daemon: &MockDaemon, token: &str, args: &[&str]) -> (i32, Value) {
+    let output = run_client(daemon, token, "json", args).await;
+    let report = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+    (output.status.code().unwrap_or(-1), report)
+}
+
+/// Runs the client with `args` against `daemon` and returns its exit code and text output.
+async fn stablecoin_text(daemon: &MockDaemon, args: &[&str]) -> (i32, String) {
+    let output = run_client(daemon, AUTH_TOKEN, "text", args).await;
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    (output.status.code().unwrap_or(-1), text)
+}
+
+async fn run_client(
+    daemon: &MockDaemon,
+    token: &str,
+    format: &str,
+    args: &[&str],
+) -> std::process::Output {
+    let config = write_config();
+    let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_tari_stable_coin_example"));
+    command
+        .arg("--config")
+        .arg(&config)
+        .args(["-e", daemon.endpoint(), "-t", token, "-o", format]);
+    // Tests about waiting set their own timeout
+    if !args.contains(&"--wait-timeout") {
+        command.args(["--wait-timeout", "5"]);
+    }
+    let output = command
+        .args(args)
+        .env_remove("STABLECOIN_PROFILE")
+        .env_remove("STABLECOIN_ACCOUNT")
+        .env_remove("STABLECOIN_MAX_FEE")
+        .env_remove("STABLECOIN_JOURNAL")
+        .output()
+        .await
+        .expect("the client can be started");
+    let _ = std::fs::remove_file(config.with_extension("jsonl"));
+    let _ = std::fs::remove_file(config);
+    output
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn every_command_submits_one_transaction_and_waits_for_it() {
+    let coin_component = deployment().coin_component.unwrap();
+    let coin_resource = deployment().coin_resource.unwrap();
+    let vault = format!("vault_{}", hex_address(0xb1));
+    let commands: Vec<(Vec<String>, usize)> = vec![
+        (vec!["increase-supply".into(), admin(), "100".into()], 4),
+        (
+            vec![
+                "decrease-supply".into(),
+                coin_component.clone(),
+                "100".into(),
+            ],
+            1,
+        ),
+        (vec!["withdraw".into(), admin(), alice(), "100".into()], 6),
+        (
+            vec![
+                "mint-and-distribute".into(),
+                admin(),
+                format!("{}=60", alice()),
+                format!("{}=40", bob()),
+            ],
+            10,
+        ),
+        (
+            vec!["deposit".into(), alice(), "5".into(), coin_resource, bob()],
+            3,
+        ),
+        (vec!["send".into(), alice(), bob(), "5".into()], 6),
+        (vec!["create-new-admin".into(), admin(), bob()], 6),
+        (
+            vec!["create-new-user".into(), admin(), "1".into(), alice()],
+            6,
+        ),
+        (vec!["blacklist-user".into(), admin(), vault, "1".into()], 4),
+        (
+            vec![
+                "remove-from-blacklist".into(),
+                coin_component.clone(),
+                "1".into(),
+            ],
+            1,
+        ),
+        (
+            vec![
+                "set-user-data".into(),
+                coin_component,
+                "1".into(),
+                "kyc".into(),
+            ],
+            1,
+        ),
+    ];
+
+    for (args, instruction_count) in commands {
+        let daemon = MockDaemon::start().await;
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let (code, report) = stablecoin(&daemon, &args).await;
+
+        assert_eq!(code, 0, "{:?} failed: {}", args, report);
+        assert_eq!(report["command"], args[0]);
+        assert_eq!(report["status"], "Accepted");
+        assert_eq!(report["fee"], 100);
+        assert_eq!(
+            daemon.methods(),
+            [
+                "transactions.submit_instruction",
+                "transactions.wait_result"
+            ],
+            "{:?}",
+            args
+        );
+
+        let requests = daemon.requests();
+        assert_eq!(requests.len(), 1, "{:?}", args);
+        let request = &requests[0];
+        assert_eq!(request.instructions.len(), instruction_count, "{:?}", args);
+        assert_eq!(request.max_fee, MAX_FEE);
+        assert!(!request.is_dry_run);
+        assert!(
+            matches!(&request.fee_account, ComponentAddressOrName::Name(name) if name == ACCOUNT)
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deposit_takes_aliases_for_both_accounts() {
+    let daemon = MockDaemon::start().await;
+    let coin_resource = deployment().coin_resource.unwrap();
+    let (code, report) =
+        stablecoin(&daemon, &["deposit", "alice", "5", &coin_resource, "bob"]).await;
+    assert_eq!(code, 0, "{}", report);
+
+    let called: Vec<_> = daemon.requests()[0]
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::CallMethod {
+                component_address,
+                method,
+                ..
+            } => Some((*component_address, method.clone())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        called,
+        [
+            (component(0xa3), "withdraw".to_string()),
+            (component(0xa2), "deposit".to_string()),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dry_run_is_not_waited_for() {
+    let daemon = MockDaemon::start().await;
+    let (code, report) = stablecoin(&daemon, &["--dry-run", "send", &alice(), &bob(), "5"]).await;
+
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["status"], "DryRun");
+    assert_eq!(daemon.methods(), ["transactions.submit_instruction"]);
+    assert!(daemon.requests()[0].is_dry_run);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn total_supply_reports_the_decoded_amount() {
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::returning(&Amount::new(1_000_000)));
+    let (code, report) = stablecoin(&daemon, &["total-supply"]).await;
+
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["total_supply"], 1_000_000);
+    assert!(daemon.requests()[0].is_dry_run);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn get_user_data_reports_the_returned_value() {
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::returning(&"kyc passed".to_string()));
+    let coin_component = deployment().coin_component.unwrap();
+    let (code, report) = stablecoin(&daemon, &["get-user-data", &coin_component, "7"]).await;
+
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["user_id"], 7);
+    assert_eq!(report["return_values"]["user_data"], "kyc passed");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_transaction_exits_with_its_own_code() {
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::rejected("user is blacklisted"));
+    let (code, report) = stablecoin(&daemon, &["send", &alice(), &bob(), "5"]).await;
+
+    assert_eq!(code, 5);
+    assert_eq!(report["status"], "Rejected");
+    assert_eq!(report["error"]["kind"], "transaction_rejected");
+    assert!(report["reject_reason"]
+        .as_str()
+        .unwrap()
+        .contains("user is blacklisted"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transaction_still_pending_after_the_wait_exits_with_its_own_code() {
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::pending());
+    let (code, report) = stablecoin(
+        &daemon,
+        &["--wait-timeout", "1", "send", &alice(), &bob(), "5"],
+    )
+    .await;
+
+    assert_eq!(code, 12, "{}", report);
+    assert_eq!(report["timed_out"], true);
+    assert_eq!(report["status"], "Pending");
+    assert_eq!(report["error"]["kind"], "timed_out");
+    assert!(report["transaction_id"].is_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn errors_the_daemon_answers_while_waiting_are_not_connection_errors() {
+    let daemon = MockDaemon::start().await;
+    let client = DaemonClient::new(
+        daemon.endpoint().to_string(),
+        Some(AUTH_TOKEN.to_string()),
+        ACCOUNT.to_string(),
+        Some(std::time::Duration::from_secs(5)),
+    );
+    // The mock does not know this transaction and says so
+    let err = client
+        .transaction_outcome(common::transaction(9))
+        .await
+        .unwrap_err();
+    assert_eq!(err.exit_code(), 9, "{}", err);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_without_a_valid_token_are_refused() {
+    let daemon = MockDaemon::start().await;
+    let (code, report) =
+        stablecoin_with_token(&daemon, "wrong-token", &["send", &alice(), &bob(), "5"]).await;
+
+    assert_eq!(
+        code, 4,
+        "a refused token is an authentication failure: {}",
+        report
+    );
+    assert!(!report["error"].is_null());
+    assert!(daemon.requests().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_addresses_are_rejected_before_contacting_the_daemon() {
+    let daemon = MockDaemon::start().await;
+    let (code, json) = stablecoin(&daemon, &["send", &alice(), "component_xyz", "5"]).await;
+
+    assert_eq!(code, 6);
+    assert_eq!(json["command"], "send");
+    assert_eq!(json["error"]["kind"], "invalid_address");
+    assert_eq!(json["error"]["exit_code"], 6);
+    assert!(daemon.methods().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn other_arguments_that_do_not_parse_are_invalid_arguments() {
+    let daemon = MockDaemon::start().await;
+    let (code, json) = stablecoin(&daemon, &["send", &alice(), &bob(), "five"]).await;
+
+    assert_eq!(code, 7);
+    assert_eq!(json["error"]["kind"], "invalid_argument");
+    assert!(daemon.methods().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn library_client_submits_through_the_daemon() {
+    let daemon = MockDaemon::start().await;
+    let client = DaemonClient::new(
+        daemon.endpoint().to_string(),
+        Some(AUTH_TOKEN.to_string()),
+        ACCOUNT.to_string(),
+        Some(std::time::Duration::from_secs(5)),
+    );
+    let mut client = StablecoinClient::new(client, deployment()).with_max_fee(MAX_FEE);
+    let (admin, alice) = (component(0xa1), component(0xa2));
+
+    let outcome = client.create_user(admin, 1, alice).await.unwrap();
+    assert!(outcome.reject_reason().is_none());
+    assert_eq!(outcome.status_name(), "Accepted");
+
+    daemon.script(Scripted::returning(&Amount::new(42)));
+    assert_eq!(client.total_supply().await.unwrap(), Amount::new(42));
+    assert_eq!(daemon.requests().len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn login_stores_the_token_from_the_daemon() {
+    let daemon = MockDaemon::start().await;
+    let dir = std::env::temp_dir().join(format!("stablecoin-login-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let status = tokio::process::Command::new(env!("CARGO_BIN_EXE_tari_stable_coin_example"))
+        .current_dir(&dir)
+        .args(["-e", daemon.endpoint(), "login"])
+        .status()
+        .await
+        .unwrap();
+
+    assert!(status.success());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("token.data")).unwrap(),
+        AUTH_TOKEN
+    );
+    assert_eq!(daemon.methods(), ["auth.request"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prepared_transaction_is_submitted_later_unchanged() {
+    let daemon = MockDaemon::start().await;
+    let file = std::env::temp_dir().join(format!("stablecoin-prepared-{}.json", process::id()));
+    let file_arg = file.to_str().unwrap();
+
+    let (code, report) = stablecoin(
+        &daemon,
+        &["--prepare", file_arg, "increase-supply", &admin(), "100"],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["status"], "Prepared");
+    assert!(daemon.methods().is_empty());
+
+    let (code, report) = stablecoin(&daemon, &["submit-prepared", "--yes", file_arg]).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["status"], "Accepted");
+    assert!(report["return_values"]["summary"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|line| line.as_str().unwrap().contains("increase_supply")));
+
+    let requests = daemon.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].instructions.len(), 4);
+    assert_eq!(requests[0].max_fee, MAX_FEE);
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prepared_transaction_is_reviewed_and_not_submitted_without_confirmation() {
+    let daemon = MockDaemon::start().await;
+    let file = std::env::temp_dir().join(format!("stablecoin-review-{}.json", process::id()));
+    let file_arg = file.to_str().unwrap();
+    let (code, report) = stablecoin(
+        &daemon,
+        &["--prepare", file_arg, "increase-supply", &admin(), "100"],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+
+    // The tests run the client without a terminal to answer a prompt on
+    let output = run_client(&daemon, AUTH_TOKEN, "json", &["submit-prepared", file_arg]).await;
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let review = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(report["error"]["kind"], "invalid_argument");
+    assert!(review.contains("increase_supply"), "{}", review);
+    assert!(
+        review.contains("Checks against the profile passed"),
+        "{}",
+        review
+    );
+    assert!(daemon.requests().is_empty());
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn prepared_transaction_must_call_the_profiles_coin_component() {
+    let daemon = MockDaemon::start().await;
+    let dir = std::env::temp_dir().join(format!("stablecoin-deployments-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let wasm = dir.join("stable_coin.wasm");
+    std::fs::write(&wasm, b"\0asm\x01\0\0\0").unwrap();
+    let publish = dir.join("publish.json");
+    let mint = dir.join("mint.json");
+    let other_coin_component = component(0x09).to_string();
+
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--prepare",
+            publish.to_str().unwrap(),
+            "publish-template",
+            wasm.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--prepare",
+            mint.to_str().unwrap(),
+            "increase-supply",
+            &admin(),
+            "100",
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+
+    // Publishing does not touch the coin component, so any deployment may submit it
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--default-coin-component",
+            &other_coin_component,
+            "submit-prepared",
+            "--yes",
+            publish.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--default-coin-component",
+            &other_coin_component,
+            "submit-prepared",
+            "--yes",
+            mint.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(code, 10);
+    assert_eq!(report["error"]["kind"], "config");
+    assert_eq!(daemon.requests().len(), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn balance_sums_the_coin_vaults_with_the_configured_decimals() {
+    let daemon = MockDaemon::start().await;
+    let coin_resource = deployment().coin_resource.unwrap().parse().unwrap();
+    let user_badge_resource = deployment().user_badge_resource.unwrap().parse().unwrap();
+    daemon.add_balance(vault(0xc1), coin_resource, 1250);
+    daemon.add_balance(vault(0xc2), coin_resource, 5);
+    daemon.add_balance(vault(0xc3), user_badge_resource, 1);
+
+    let (code, report) = stablecoin(&daemon, &["--decimals", "2", "balance", "--all"]).await;
+
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(values["account"], ACCOUNT);
+    assert_eq!(values["balance"], "12.55");
+    assert_eq!(values["raw_balance"], 1255);
+    assert_eq!(values["vaults"].as_array().unwrap().len(), 2);
+    assert_eq!(values["other_resources"][0]["name"], "user_badge");
+    assert_eq!(values["other_resources"][0]["balance"], 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklisting_is_recorded_in_the_journal() {
+    let daemon = MockDaemon::start().await;
+    let journal = std::env::temp_dir().join(format!("stablecoin-journal-{}.jsonl", process::id()));
+    let journal_arg = journal.to_str().unwrap();
+    let vault = format!("vault_{}", hex_address(0xb1));
+
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--journal",
+            journal_arg,
+            "blacklist-user",
+            &admin(),
+            &vault,
+            "7",
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--journal",
+            journal_arg,
+            "--dry-run",
+            "blacklist-user",
+            &admin(),
+            &vault,
+            "8",
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+
+    let entries = Journal::new(journal.clone()).entries().unwrap();
+    assert_eq!(entries.len(), 1, "dry runs are not recorded");
+    let entry = &entries[0];
+    assert_eq!(entry.action, JournalAction::Blacklist);
+    assert_eq!(entry.user_id, 7);
+    assert_eq!(entry.vault.as_deref(), Some(vault.as_str()));
+    assert_eq!(entry.admin_account, Some(admin()));
+    assert_eq!(entry.wallet_account, ACCOUNT);
+    assert!(entry.transaction_id.is_some());
+    std::fs::remove_file(journal).unwrap();
+}
+
+/// An indexer holding three user badges: alice holds user 1, the coin component has recalled
+/// user 2 and user 3's badge has been burnt.
+async fn user_badges() -> MockIndexer {
+    let indexer = MockIndexer::start().await;
+    let user_badge_resource = resource(0x03);
+    indexer.add_non_fungible_vault(component(0xa2), vault(0xb2), user_badge_resource, &[1]);
+    indexer.add_fungible_vault(component(0xa2), vault(0xc2), resource(0x04), 50);
+    indexer.add_component(component(0xa3));
+    indexer.add_non_fungible_vault(component(0x01), vault(0xb0), user_badge_resource, &[2]);
+    indexer.mint(user_badge_resource, 1, false);
+    indexer.mint(user_badge_resource, 2, false);
+    indexer.mint(user_badge_resource, 3, true);
+    indexer
+}
+
+/// The treasury pays alice 100 in transaction 1, alice sends 40 of it to bob in 2 and bob sends
+/// 10 back in 3.
+async fn coin_flows() -> MockIndexer {
+    let indexer = MockIndexer::start().await;
+    let (treasury, alice, bob) = (vault(0xc0), vault(0xc2), vault(0xc3));
+    indexer.add_fungible_vault(component(0x01), treasury, resource(0x04), 1000);
+    indexer.add_fungible_vault(component(0xa2), alice, resource(0x04), 0);
+    indexer.add_fungible_vault(component(0xa3), bob, resource(0x04), 0);
+    indexer.transact(common::transaction(1), &[(treasury, -100), (alice, 100)]);
+    indexer.transact(common::transaction(2), &[(alice, -40), (bob, 40)]);
+    indexer.transact(common::transaction(3), &[(bob, -10), (alice, 10)]);
+    indexer
+}
+
+fn transaction_arg(byte: u8) -> String {
+    transaction_hex(&common::transaction(byte))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_transaction_follows_the_coins_back_to_where_they_came_from() {
+    let daemon = MockDaemon::start().await;
+    let indexer = coin_flows().await;
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "trace",
+            "--transaction",
+            &transaction_arg(2),
+            "--direction",
+            "backward",
+        ],
+    )
+    .await;
+
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(
+        values["transactions"], 3,
+        "2, then 1, then the treasury's creation"
+    );
+    let tree: Vec<_> = values["tree"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|line| line.as_str().unwrap())
+        .collect();
+    assert_eq!(tree[0], format!("transaction {}", transaction_arg(2)));
+    let funded_by = format!(
+        "  funded via alice ({}) by transaction {}",
+        vault(0xc2),
+        transaction_arg(1)
+    );
+    assert!(tree.contains(&funded_by.as_str()), "{:#?}", tree);
+    assert!(
+        !tree.iter().any(|line| line.contains(&transaction_arg(3))),
+        "going backward never reaches the later transaction: {:#?}",
+        tree
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_transaction_follows_the_coins_forward_as_deep_as_asked() {
+    let daemon = MockDaemon::start().await;
+    let indexer = coin_flows().await;
+    let transaction = transaction_arg(1);
+
+    // Alice spent the coins in 2 and bob spent them on in 3
+    for (depth, reached) in [("1", 2), ("3", 3)] {
+        let (code, report) = stablecoin(
+            &daemon,
+            &[
+                "--indexer-endpoint",
+                indexer.endpoint(),
+                "trace",
+                "--transaction",
+                &transaction,
+                "--direction",
+                "forward",
+                "--depth",
+                depth,
+            ],
+        )
+        .await;
+        assert_eq!(code, 0, "{}", report);
+        assert_eq!(
+            report["return_values"]["transactions"], reached,
+            "depth {}",
+            depth
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_account_starts_from_its_latest_transactions_in_both_directions() {
+    let daemon = MockDaemon::start().await;
+    let indexer = coin_flows().await;
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "trace",
+            "--account",
+            "bob",
+            "--limit",
+            "1",
+            "--depth",
+            "1",
+        ],
+    )
+    .await;
+
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(values["transactions"], 2);
+    let tree = values["tree"].as_array().unwrap();
+    assert_eq!(tree[0], format!("transaction {}", transaction_arg(3)));
+    assert!(
+        tree.iter().any(|line| line
+            == &format!(
+                "  funded via bob ({}) by transaction {}",
+                vault(0xc3),
+                transaction_arg(2)
+            )),
+        "{:#?}",
+        tree
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn audit_supply_is_reconciled_only_when_every_coin_is_found() {
+    let indexer = user_badges().await;
+    let args = ["--indexer-endpoint", indexer.endpoint(), "audit", "supply"];
+
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::returning(&Amount::new(50)));
+    let (code, report) = stablecoin(&daemon, &args).await;
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(values["reconciled"], true);
+    assert_eq!(values["raw_discrepancy"], 0);
+    assert_eq!(values["components_searched"], 3);
+    assert_eq!(
+        values["unresolved_blacklisted_users"],
+        serde_json::json!([2]),
+        "the journal does not say whose badge the coin component holds"
+    );
+
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::returning(&Amount::new(80)));
+    let (code, report) = stablecoin(&daemon, &args).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["reconciled"], false);
+    assert_eq!(report["return_values"]["raw_discrepancy"], 30);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn users_list_reports_each_badge_with_its_holder() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let (code, report) = stablecoin(
+        &daemon,
+        &["--indexer-endpoint", indexer.endpoint(), "users", "list"],
+    )
+    .await;
+
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(values["total"], 3);
+    assert!(values.get("next_offset").is_none());
+    let users = values["users"].as_array().unwrap();
+    assert_eq!(users.len(), 3);
+
+    assert_eq!(users[0]["user_id"], 1);
+    assert_eq!(users[0]["holder"], "alice");
+    assert_eq!(users[0]["account"], alice());
+    assert_eq!(users[0]["vault"], format!("vault_{}", hex_address(0xb2)));
+    assert_eq!(users[0]["blacklisted"], false);
+    assert_eq!(users[0]["burnt"], false);
+
+    assert_eq!(users[1]["user_id"], 2);
+    assert_eq!(users[1]["account"], component(0x01).to_string());
+    assert_eq!(users[1]["blacklisted"], true);
+
+    assert_eq!(users[2]["user_id"], 3);
+    assert_eq!(users[2]["holder"], Value::Null);
+    assert_eq!(users[2]["burnt"], true);
+    assert!(daemon.requests().is_empty(), "listing submits nothing");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn users_list_pages_through_the_badges() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let endpoint = indexer.endpoint();
+    let args = [
+        "--indexer-endpoint",
+        endpoint,
+        "users",
+        "list",
+        "--limit",
+        "2",
+    ];
+    let (code, report) = stablecoin(&daemon, &args).await;
+
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(
+        report["return_values"]["users"].as_array().unwrap().len(),
+        2
+    );
+    assert_eq!(report["return_values"]["next_offset"], 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn users_list_prints_one_line_per_user() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let (code, text) = stablecoin_text(
+        &daemon,
+        &["--indexer-endpoint", indexer.endpoint(), "users", "list"],
+    )
+    .await;
+
+    assert_eq!(code, 0, "{}", text);
+    assert!(text.contains("total: 3"), "{}", text);
+    let lines: Vec<_> = text.lines().filter(|line| line.starts_with("  ")).collect();
+    assert_eq!(lines.len(), 3, "{}", text);
+    assert!(lines[0].contains("holder: alice"), "{}", text);
+    assert!(lines[0].contains("user_id: 1"), "{}", text);
+    assert!(lines[2].contains("burnt: true"), "{}", text);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn users_list_exports_csv() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let export = std::env::temp_dir().join(format!("stablecoin-users-{}.csv", process::id()));
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "users",
+            "list",
+            "--export",
+            export.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(code, 0, "{}", report);
+
+    let contents = std::fs::read_to_string(&export).unwrap();
+    let lines: Vec<_> = contents.lines().collect();
+    assert_eq!(
+        lines[0],
+        "user_id,holder,account,vault,blacklisted,burnt,data"
+    );
+    assert_eq!(lines.len(), 4, "{}", contents);
+    assert!(lines[1].starts_with(&format!(
+        "1,alice,{},vault_{},false,false,",
+        alice(),
+        hex_address(0xb2)
+    )));
+    assert!(lines[3].starts_with("3,,,,false,true,"), "{}", contents);
+    std::fs::remove_file(export).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklist_user_looks_up_the_badge_an_account_holds() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "blacklist-user",
+            &admin(),
+            "--account",
+            "alice",
+        ],
+    )
+    .await;
+
+    assert_eq!(code, 0, "{}", report);
+    let values = &report["return_values"];
+    assert_eq!(values["account"], alice());
+    assert_eq!(values["user_id"], 1);
+    assert_eq!(
+        values["badge_vault"],
+        format!("vault_{}", hex_address(0xb2))
+    );
+    assert_eq!(values["coin_vault"], format!("vault_{}", hex_address(0xc2)));
+
+    let requests = daemon.requests();
+    assert_eq!(requests.len(), 1);
+    let inputs: Vec<_> = requests[0]
+        .inputs
+        .iter()
+        .map(|i| i.address().clone())
+        .collect();
+    assert!(
+        inputs.contains(&SubstateAddress::Vault(vault(0xb2))),
+        "{:?}",
+        inputs
+    );
+    let badge = NonFungibleAddress::new(resource(0x03), NonFungibleId::from_u64(1));
+    assert!(
+        inputs.contains(&SubstateAddress::NonFungible(badge)),
+        "{:?}",
+        inputs
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklist_user_refuses_an_account_without_a_badge() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "blacklist-user",
+            &admin(),
+            "--account",
+            "bob",
+        ],
+    )
+    .await;
+
+    assert_eq!(code, 7, "{}", report);
+    assert_eq!(report["error"]["kind"], "invalid_argument");
+    assert!(report["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("holds no user badge"));
+    assert!(daemon.requests().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklist_user_refuses_an_account_with_several_badges() {
+    let daemon = MockDaemon::start().await;
+    let indexer = user_badges().await;
+    indexer.add_non_fungible_vault(component(0xa3), vault(0xb3), resource(0x03), &[4, 5]);
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "--indexer-endpoint",
+            indexer.endpoint(),
+            "blacklist-user",
+            &admin(),
+            "--account",
+            "bob",
+        ],
+    )
+    .await;
+
+    assert_eq!(code, 7, "{}", report);
+    assert_eq!(report["error"]["kind"], "invalid_argument");
+    assert!(report["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("several user badges"));
+    assert!(daemon.requests().is_empty());
+}
+
+/// Writes a payout CSV to a temporary file and returns its path and the state file next to it.
+fn write_payouts(name: &str, rows: &[(String, u64)]) -> (PathBuf, PathBuf) {
+    let path = std::env::temp_dir().join(format!("stablecoin-{}-{}.csv", name, process::id()));
+    let mut contents = "recipient,amount\n".to_string();
+    for (recipient, amount) in rows {
+        contents.push_str(&format!("{},{}\n", recipient, amount));
+    }
+    std::fs::write(&path, contents).unwrap();
+    let state = BatchState::default_path(&path);
+    let _ = std::fs::remove_file(&state);
+    (path, state)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch_send_pays_each_row_once_across_runs() {
+    let daemon = MockDaemon::start().await;
+    let (csv, state) = write_payouts("batch", &[(alice(), 5), (bob(), 7), (alice(), 9)]);
+    let csv_arg = csv.to_str().unwrap();
+
+    let (code, report) = stablecoin(&daemon, &["batch-send", &admin(), csv_arg]).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["paid"], 3);
+    let requests = daemon.requests();
+    assert_eq!(requests.len(), 2, "a dry run and the payment");
+    assert!(requests[0].is_dry_run);
+    assert!(!requests[1].is_dry_run);
+    assert_eq!(
+        requests[1].instructions.len(),
+        12,
+        "a withdraw and a deposit for each row"
+    );
+
+    let (code, report) = stablecoin(&daemon, &["batch-send", &admin(), csv_arg]).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["paid"], 3);
+    assert_eq!(daemon.requests().len(), 2, "nothing is paid twice");
+
+    let saved = BatchState::load(&state).unwrap().unwrap();
+    assert!(saved
+        .payments
+        .iter()
+        .all(|p| p.status == PaymentStatus::Paid && p.transaction_id.is_some()));
+    std::fs::remove_file(csv).unwrap();
+    std::fs::remove_file(state).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch_send_splits_rejected_transactions_down_to_the_failing_row() {
+    let daemon = MockDaemon::start().await;
+    for scripted in [
+        Scripted::rejected("insufficient funds"),
+        Scripted::accepted(),
+        Scripted::accepted(),
+        Scripted::rejected("insufficient funds"),
+    ] {
+        daemon.script(scripted);
+    }
+    let (csv, state) = write_payouts("split", &[(alice(), 5), (bob(), 700), (alice(), 9)]);
+
+    let (code, report) = stablecoin(
+        &daemon,
+        &[
+            "batch-send",
+            "--max-rows",
+            "2",
+            &admin(),
+            csv.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(code, 5, "a failed row fails the command: {}", report);
+    assert_eq!(report["error"]["kind"], "transaction_rejected");
+    let values = &report["return_values"];
+    assert_eq!(values["paid"], 2);
+    assert_eq!(values["failed"], 1);
+    assert_eq!(values["rows"][1]["status"], "failed");
+    assert_eq!(values["rows"][1]["recipient"], bob());
+
+    let payments: Vec<_> = daemon
+        .requests()
+        .into_iter()
+        .filter(|r| !r.is_dry_run)
+        .map(|r| r.instructions.len())
+        .collect();
+    assert_eq!(payments, [6, 6], "the other rows are paid one at a time");
+    std::fs::remove_file(csv).unwrap();
+    std::fs::remove_file(state).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch_send_records_the_transaction_before_waiting_for_it() {
+    let daemon = MockDaemon::start().await;
+    daemon.script(Scripted::accepted());
+    daemon.script(Scripted::rejected("vault is frozen"));
+    let (csv, state) = write_payouts("rejected", &[(alice(), 5), (bob(), 7)]);
+
+    let (code, report) =
+        stablecoin(&daemon, &["batch-send", &admin(), csv.to_str().unwrap()]).await;
+    assert_eq!(code, 5, "{}", report);
+    assert_eq!(report["return_values"]["failed"], 2);
+    assert_eq!(
+        daemon.methods(),
+        [
+            "transactions.submit_instruction",
+            "transactions.submit_instruction",
+            "transactions.wait_result",
+        ]
+    );
+
+    let saved = BatchState::load(&state).unwrap().unwrap();
+    for payment in &saved.payments {
+        assert_eq!(payment.status, PaymentStatus::Failed);
+        assert!(payment.transaction_id.is_some());
+        assert!(payment
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("vault is frozen"));
+    }
+    std::fs::remove_file(csv).unwrap();
+    std::fs::remove_file(state).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch_send_looks_up_submitted_rows_by_transaction_id() {
+    let daemon = MockDaemon::start().await;
+    let (csv, state) = write_payouts("no-wait", &[(alice(), 5), (bob(), 7)]);
+    let csv_arg = csv.to_str().unwrap();
+
+    let (code, report) = stablecoin(&daemon, &["--no-wait", "batch-send", &admin(), csv_arg]).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["submitted"], 2);
+    let saved = BatchState::load(&state).unwrap().unwrap();
+    let transaction_id = saved.payments[0].transaction_id.clone();
+    assert!(transaction_id.is_some());
+    assert!(saved
+        .payments
+        .iter()
+        .all(|p| p.status == PaymentStatus::Submitted && p.transaction_id == transaction_id));
+
+    let (code, report) = stablecoin(&daemon, &["batch-send", &admin(), csv_arg]).await;
+    assert_eq!(code, 0, "{}", report);
+    assert_eq!(report["return_values"]["paid"], 2);
+    assert_eq!(daemon.requests().len(), 2, "nothing is sent again");
+    assert_eq!(
+        daemon.methods().last().map(String::as_str),
+        Some("transactions.wait_result")
+    );
+    let saved = BatchState::load(&state).unwrap().unwrap();
+    assert!(saved
+        .payments
+        .iter()
+        .all(|p| p.status == PaymentStatus::Paid && p.transaction_id == transaction_id));
+    std::fs::remove_file(csv).unwrap();
+    std::fs::remove_file(state).unwrap();
+}
@@ -0,0 +1,317 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! An in-process stand-in for `tari_indexer`. It serves the components, vaults and
+//! non-fungibles a test adds, the way the indexer's JSON-RPC methods return them.
+//!
+//! Vaults are added by [`transaction`]`(0)`, at version 0. Each transaction recorded with
+//! [`MockIndexer::transact`] creates the next version of the vaults it changes, so coins can be
+//! followed from version to version.
+
+use super::finalize_result;
+use super::listen;
+use super::method_and_params;
+use super::response;
+use super::transaction;
+use super::INVALID_PARAMS;
+use super::METHOD_NOT_FOUND;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tari_engine_types::commit_result::ExecuteResult;
+use tari_engine_types::commit_result::TransactionResult;
+use tari_engine_types::component::ComponentBody;
+use tari_engine_types::component::ComponentHeader;
+use tari_engine_types::non_fungible::NonFungible;
+use tari_engine_types::non_fungible::NonFungibleContainer;
+use tari_engine_types::resource_container::ResourceContainer;
+use tari_engine_types::substate::Substate;
+use tari_engine_types::substate::SubstateAddress;
+use tari_engine_types::substate::SubstateDiff;
+use tari_engine_types::substate::SubstateValue;
+use tari_engine_types::vault::Vault;
+use tari_indexer_client::types::GetNonFungibleCountRequest;
+use tari_indexer_client::types::GetNonFungibleCountResponse;
+use tari_indexer_client::types::GetNonFungiblesRequest;
+use tari_indexer_client::types::GetNonFungiblesResponse;
+use tari_indexer_client::types::GetSubstateRequest;
+use tari_indexer_client::types::GetSubstateResponse;
+use tari_indexer_client::types::GetTransactionResultRequest;
+use tari_indexer_client::types::NonFungibleSubstate;
+use tari_template_lib::models::Amount;
+use tari_template_lib::models::NonFungibleAddress;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+
+/// JSON-RPC error code for substates the indexer does not know
+const NOT_FOUND: i64 = 404;
+
+#[derive(Debug, Default)]
+struct State {
+    /// The vaults referenced from each component's state
+    components: BTreeMap<ComponentAddress, Vec<VaultId>>,
+    /// Every version of each vault, oldest first, with the transaction that created it
+    vaults: HashMap<VaultId, Vec<(Vault, TransactionId)>>,
+    /// The vault versions each transaction created
+    transactions: HashMap<TransactionId, Vec<(VaultId, u32)>>,
+    /// Every token minted, in the order they were minted
+    non_fungibles: Vec<(ResourceAddress, NonFungibleId, NonFungibleContainer)>,
+    methods: Vec<String>,
+}
+
+/// A running mock indexer. It stops when the test's runtime shuts down.
+#[derive(Debug, Clone)]
+pub struct MockIndexer {
+    endpoint: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockIndexer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let handle_state = state.clone();
+        // The indexer does not ask for a token
+        let endpoint = listen(move |request, _| handle(&handle_state, request)).await;
+        Self { endpoint, state }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Adds a component with no vaults. Adding a vault to a component also adds the component.
+    pub fn add_component(&self, component: ComponentAddress) {
+        self.state
+            .lock()
+            .unwrap()
+            .components
+            .entry(component)
+            .or_default();
+    }
+
+    /// Adds a vault of `component` holding `balance` of the fungible `resource`.
+    pub fn add_fungible_vault(
+        &self,
+        component: ComponentAddress,
+        vault: VaultId,
+        resource: ResourceAddress,
+        balance: i64,
+    ) {
+        let container = ResourceContainer::fungible(resource, Amount::new(balance));
+        self.add_vault(component, vault, container);
+    }
+
+    /// Adds a vault of `component` holding the tokens of `resource` with the ids `ids`.
+    pub fn add_non_fungible_vault(
+        &self,
+        component: ComponentAddress,
+        vault: VaultId,
+        resource: ResourceAddress,
+        ids: &[u64],
+    ) {
+        let ids = ids.iter().map(|id| NonFungibleId::from_u64(*id)).collect();
+        self.add_vault(
+            component,
+            vault,
+            ResourceContainer::non_fungible(resource, ids),
+        );
+    }
+
+    fn add_vault(&self, component: ComponentAddress, vault: VaultId, container: ResourceContainer) {
+        let mut state = self.state.lock().unwrap();
+        state.components.entry(component).or_default().push(vault);
+        state
+            .vaults
+            .insert(vault, vec![(Vault::new(container), transaction(0))]);
+        state
+            .transactions
+            .entry(transaction(0))
+            .or_default()
+            .push((vault, 0));
+    }
+
+    /// Records `transaction_id` as changing the balance of each fungible vault in `changes` by
+    /// its amount, creating the next version of the vault.
+    pub fn transact(&self, transaction_id: TransactionId, changes: &[(VaultId, i64)]) {
+        let mut state = self.state.lock().unwrap();
+        for &(vault_id, change) in changes {
+            let versions = state
+                .vaults
+                .get_mut(&vault_id)
+                .expect("the vault was added");
+            let (latest, _) = versions.last().expect("vaults have a version");
+            let balance = latest.balance().value() + change;
+            let container =
+                ResourceContainer::fungible(*latest.resource_address(), Amount::new(balance));
+            versions.push((Vault::new(container), transaction_id));
+            let version = versions.len() as u32 - 1;
+            state
+                .transactions
+                .entry(transaction_id)
+                .or_default()
+                .push((vault_id, version));
+        }
+    }
+
+    /// Records that the token `id` of `resource` was minted, and burnt since if `burnt`.
+    pub fn mint(&self, resource: ResourceAddress, id: u64, burnt: bool) {
+        let container = if burnt {
+            NonFungibleContainer::no_contents()
+        } else {
+            let data = tari_bor::to_value(&id).expect("ids can be encoded");
+            let mutable_data = tari_bor::to_value(&()).expect("unit can be encoded");
+            NonFungibleContainer::new(NonFungible::new(data, mutable_data))
+        };
+        self.state.lock().unwrap().non_fungibles.push((
+            resource,
+            NonFungibleId::from_u64(id),
+            container,
+        ));
+    }
+
+    /// The JSON-RPC methods called so far, in order.
+    pub fn methods(&self) -> Vec<String> {
+        self.state.lock().unwrap().methods.clone()
+    }
+}
+
+fn handle(state: &Mutex<State>, request: &Value) -> Value {
+    let (method, params) = method_and_params(request);
+    let mut state = state.lock().unwrap();
+    state.methods.push(method.to_string());
+
+    let result = match method {
+        "get_substate" => get_substate(&state, params),
+        "get_transaction_result" => get_transaction_result(&state, params),
+        "get_non_fungible_count" => get_non_fungible_count(&state, params),
+        "get_non_fungibles" => get_non_fungibles(&state, params),
+        _ => Err((METHOD_NOT_FOUND, format!("method '{}' not found", method))),
+    };
+    response(request, result)
+}
+
+fn get_substate(state: &State, params: Value) -> Result<Value, (i64, String)> {
+    let request: GetSubstateRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let found = match &request.address {
+        SubstateAddress::Component(address) => state.components.get(address).map(|vaults| {
+            let component = SubstateValue::Component(component_header(vaults));
+            (component, 0, transaction(0))
+        }),
+        SubstateAddress::Vault(vault_id) => state.vaults.get(vault_id).and_then(|versions| {
+            let version = request.version.unwrap_or(versions.len() as u32 - 1);
+            let (vault, created_by) = versions.get(version as usize)?;
+            Some((SubstateValue::Vault(vault.clone()), version, *created_by))
+        }),
+        _ => None,
+    };
+    let (substate, version, created_by) =
+        found.ok_or_else(|| (NOT_FOUND, format!("{} not found", request.address)))?;
+
+    Ok(json!(GetSubstateResponse {
+        address: request.address,
+        version,
+        substate,
+        created_by_transaction: created_by,
+    }))
+}
+
+/// The result of a recorded transaction: it consumed the version before each vault version it
+/// created.
+fn get_transaction_result(state: &State, params: Value) -> Result<Value, (i64, String)> {
+    let request: GetTransactionResultRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let created = state
+        .transactions
+        .get(&request.transaction_id)
+        .ok_or_else(|| (NOT_FOUND, format!("{} not found", request.transaction_id)))?;
+
+    let mut diff = SubstateDiff::new();
+    for &(vault_id, version) in created {
+        let address = SubstateAddress::Vault(vault_id);
+        if version > 0 {
+            diff.down(address.clone(), version - 1);
+        }
+        let (vault, _) = &state.vaults[&vault_id][version as usize];
+        diff.up(
+            address,
+            Substate::new(version, SubstateValue::Vault(vault.clone())),
+        );
+    }
+    let execution_result = ExecuteResult {
+        finalize: finalize_result(TransactionResult::Accept(diff), vec![]),
+    };
+    // Written out because the decision type is not in a crate the tests depend on
+    Ok(json!({
+        "result": {
+            "Finalized": {
+                "final_decision": "Commit",
+                "execution_result": execution_result,
+                "execution_time": { "secs": 0, "nanos": 0 },
+                "finalized_time": { "secs": 0, "nanos": 0 },
+                "abort_details": null,
+                "json_results": [],
+            }
+        }
+    }))
+}
+
+/// An account-like component whose state is the list of its vaults.
+fn component_header(vaults: &[VaultId]) -> ComponentHeader {
+    ComponentHeader {
+        template_address: Default::default(),
+        module_name: "Account".to_string(),
+        owner_key: Default::default(),
+        access_rules: Default::default(),
+        state: ComponentBody {
+            state: tari_bor::encode(&vaults).expect("vault ids can be encoded"),
+        },
+    }
+}
+
+fn get_non_fungible_count(state: &State, params: Value) -> Result<Value, (i64, String)> {
+    let request: GetNonFungibleCountRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let resource = resource_of(&request.address)?;
+    let count = state
+        .non_fungibles
+        .iter()
+        .filter(|(r, _, _)| *r == resource)
+        .count();
+    Ok(json!(GetNonFungibleCountResponse {
+        count: count as u64
+    }))
+}
+
+fn get_non_fungibles(state: &State, params: Value) -> Result<Value, (i64, String)> {
+    let request: GetNonFungiblesRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let resource = resource_of(&request.address)?;
+    let non_fungibles = state
+        .non_fungibles
+        .iter()
+        .filter(|(r, _, _)| *r == resource)
+        .enumerate()
+        .skip(request.start_index as usize)
+        .take(request.end_index.saturating_sub(request.start_index) as usize)
+        .map(|(index, (resource, id, container))| NonFungibleSubstate {
+            index: index as u64,
+            address: SubstateAddress::NonFungible(NonFungibleAddress::new(*resource, id.clone())),
+            substate: Substate::new(0, SubstateValue::NonFungible(container.clone())),
+        })
+        .collect();
+    Ok(json!(GetNonFungiblesResponse { non_fungibles }))
+}
+
+fn resource_of(address: &SubstateAddress) -> Result<ResourceAddress, (i64, String)> {
+    match address {
+        SubstateAddress::Resource(resource) => Ok(*resource),
+        address => Err((INVALID_PARAMS, format!("{} is not a resource", address))),
+    }
+}
@@ -0,0 +1,438 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! An in-process stand-in for `tari_dan_wallet_daemon`. It serves the JSON-RPC methods this
+//! client uses over HTTP on a local port, records every `CallInstructionRequest` it is sent and
+//! answers with results scripted by the test.
+//!
+//! [`MockIndexer`] does the same for `tari_indexer`, serving substates added by the test.
+//!
+//! Also holds the fixture addresses the tests share. Each is one byte repeated, so the byte
+//! names it: 0x01 is the coin component, 0xa1 to 0xa3 are the admin, alice and bob.
+
+#![allow(dead_code)]
+
+mod indexer;
+
+pub use indexer::MockIndexer;
+
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tari_dan_wallet_sdk::models::TransactionStatus;
+use tari_engine_types::commit_result::ExecuteResult;
+use tari_engine_types::commit_result::FinalizeResult;
+use tari_engine_types::commit_result::RejectReason;
+use tari_engine_types::commit_result::TransactionResult;
+use tari_engine_types::fees::FeeReceipt;
+use tari_engine_types::indexed_value::IndexedValue;
+use tari_engine_types::instruction_result::InstructionResult;
+use tari_engine_types::substate::SubstateAddress;
+use tari_engine_types::substate::SubstateDiff;
+use tari_template_abi::Type;
+use tari_template_lib::models::Amount;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::ResourceType;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+use tari_wallet_daemon_client::types::AccountsGetBalancesRequest;
+use tari_wallet_daemon_client::types::AccountsGetBalancesResponse;
+use tari_wallet_daemon_client::types::AccountsListResponse;
+use tari_wallet_daemon_client::types::AuthLoginResponse;
+use tari_wallet_daemon_client::types::BalanceEntry;
+use tari_wallet_daemon_client::types::CallInstructionRequest;
+use tari_wallet_daemon_client::types::TransactionSubmitResponse;
+use tari_wallet_daemon_client::types::TransactionWaitResultRequest;
+use tari_wallet_daemon_client::types::TransactionWaitResultResponse;
+use tari_wallet_daemon_client::ComponentAddressOrName;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+pub const AUTH_TOKEN: &str = "mock-auth-token";
+
+/// JSON-RPC error code for requests without a valid auth token
+const UNAUTHORIZED: i64 = 401;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// The hex of a 32 byte address made of `byte` repeated.
+pub fn hex_address(byte: u8) -> String {
+    format!("{:02x}", byte).repeat(32)
+}
+
+pub fn component(byte: u8) -> ComponentAddress {
+    format!("component_{}", hex_address(byte)).parse().unwrap()
+}
+
+pub fn resource(byte: u8) -> ResourceAddress {
+    format!("resource_{}", hex_address(byte)).parse().unwrap()
+}
+
+pub fn vault(byte: u8) -> VaultId {
+    VaultId::from_hex(&hex_address(byte)).unwrap()
+}
+
+pub fn transaction(byte: u8) -> TransactionId {
+    TransactionId::new([byte; 32])
+}
+
+/// What the mock answers to the next submitted transaction.
+#[derive(Debug, Clone)]
+pub struct Scripted {
+    pub result: FinalizeResult,
+    pub json_result: Vec<Value>,
+    pub fee: u64,
+    /// Never finalizes: waiting for it always times out
+    pub pending: bool,
+}
+
+impl Scripted {
+    /// An accepted transaction that returns nothing.
+    pub fn accepted() -> Self {
+        Self {
+            result: finalize_result(TransactionResult::Accept(SubstateDiff::new()), vec![]),
+            json_result: vec![],
+            fee: 100,
+            pending: false,
+        }
+    }
+
+    /// A transaction that stays pending however long it is waited for.
+    pub fn pending() -> Self {
+        Self {
+            pending: true,
+            ..Self::accepted()
+        }
+    }
+
+    /// An accepted transaction whose last instruction returned `value`.
+    pub fn returning<T: Serialize>(value: &T) -> Self {
+        let execution_result = InstructionResult {
+            indexed: IndexedValue::from_type(value).expect("value can be encoded"),
+            return_type: Type::Other {
+                name: std::any::type_name::<T>().to_string(),
+            },
+        };
+        Self {
+            result: finalize_result(
+                TransactionResult::Accept(SubstateDiff::new()),
+                vec![execution_result],
+            ),
+            json_result: vec![serde_json::to_value(value).expect("value can be encoded")],
+            fee: 100,
+            pending: false,
+        }
+    }
+
+    pub fn rejected(reason: &str) -> Self {
+        Self {
+            result: finalize_result(
+                TransactionResult::Reject(RejectReason::ExecutionFailure(reason.to_string())),
+                vec![],
+            ),
+            json_result: vec![],
+            fee: 0,
+            pending: false,
+        }
+    }
+
+    fn status(&self) -> TransactionStatus {
+        if self.pending {
+            return TransactionStatus::Pending;
+        }
+        match self.result.result {
+            TransactionResult::Accept(_) => TransactionStatus::Accepted,
+            TransactionResult::AcceptFeeRejectRest(_, _) => TransactionStatus::OnlyFeeAccepted,
+            TransactionResult::Reject(_) => TransactionStatus::Rejected,
+        }
+    }
+}
+
+fn finalize_result(
+    result: TransactionResult,
+    execution_results: Vec<InstructionResult>,
+) -> FinalizeResult {
+    let mut finalize = FinalizeResult::new(
+        Default::default(),
+        vec![],
+        vec![],
+        result,
+        FeeReceipt::default(),
+    );
+    finalize.execution_results = execution_results;
+    finalize
+}
+
+#[derive(Debug, Default)]
+struct State {
+    requests: Vec<CallInstructionRequest>,
+    methods: Vec<String>,
+    script: VecDeque<Scripted>,
+    pending: HashMap<TransactionId, Scripted>,
+    balances: Vec<BalanceEntry>,
+    next_transaction: u64,
+}
+
+/// A running mock daemon. It stops when the test's runtime shuts down.
+#[derive(Debug, Clone)]
+pub struct MockDaemon {
+    endpoint: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockDaemon {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let handle_state = state.clone();
+        let endpoint =
+            listen(move |request, authorized| handle(&handle_state, request, authorized)).await;
+        Self { endpoint, state }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Queues the result for the next submitted transaction. Transactions submitted with an
+    /// empty queue are accepted and return nothing.
+    pub fn script(&self, scripted: Scripted) {
+        self.state.lock().unwrap().script.push_back(scripted);
+    }
+
+    /// Adds a vault holding `balance` of `resource` to the balances reported for any account.
+    pub fn add_balance(&self, vault: VaultId, resource: ResourceAddress, balance: i64) {
+        self.state.lock().unwrap().balances.push(BalanceEntry {
+            vault_address: SubstateAddress::Vault(vault),
+            resource_address: resource,
+            balance: Amount::new(balance),
+            resource_type: ResourceType::Fungible,
+            confidential_balance: Amount::zero(),
+            token_symbol: None,
+        });
+    }
+
+    /// Every `CallInstructionRequest` submitted so far, in order.
+    pub fn requests(&self) -> Vec<CallInstructionRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The JSON-RPC methods called so far, in order, including rejected calls.
+    pub fn methods(&self) -> Vec<String> {
+        self.state.lock().unwrap().methods.clone()
+    }
+}
+
+/// Serves JSON-RPC over HTTP on a local port, answering each request with `handle`, which is
+/// also told whether the request carried the mock's auth token. Returns the endpoint.
+async fn listen<H>(handle: H) -> String
+where
+    H: Fn(&Value, bool) -> Value + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("can bind a local port");
+    let endpoint = format!(
+        "http://{}/json_rpc",
+        listener.local_addr().expect("listener has an address")
+    );
+
+    let handle = Arc::new(handle);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                // A client hanging up mid-request is not the test's concern
+                let _ = serve(stream, handle.as_ref()).await;
+            });
+        }
+    });
+    endpoint
+}
+
+async fn serve<H>(mut stream: TcpStream, handle: &H) -> io::Result<()>
+where
+    H: Fn(&Value, bool) -> Value,
+{
+    let (headers, body) = read_request(&mut stream).await?;
+    let authorized = headers.lines().any(|line| {
+        line.split_once(':').map_or(false, |(name, value)| {
+            name.trim().eq_ignore_ascii_case("authorization")
+                && value.trim() == format!("Bearer {}", AUTH_TOKEN)
+        })
+    });
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let response = handle(&request, authorized);
+
+    let body = serde_json::to_vec(&response).expect("responses are serializable");
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+/// Reads one HTTP request, returning its headers and body.
+async fn read_request(stream: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&buf[..end]).to_string();
+        let content_length = headers
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while buf.len() < end + 4 + content_length {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        return Ok((headers, buf[end + 4..end + 4 + content_length].to_vec()));
+    }
+}
+
+fn handle(state: &Mutex<State>, request: &Value, authorized: bool) -> Value {
+    let (method, params) = method_and_params(request);
+    let mut state = state.lock().unwrap();
+    state.methods.push(method.to_string());
+
+    let result = match method {
+        "auth.request" => Ok(json!(AuthLoginResponse {
+            auth_token: AUTH_TOKEN.to_string(),
+            valid_for_secs: 0,
+        })),
+        _ if !authorized => Err((UNAUTHORIZED, "invalid or missing auth token".to_string())),
+        "transactions.submit_instruction" => submit_instruction(&mut state, params),
+        "transactions.wait_result" => wait_result(&mut state, params),
+        "accounts.get_balances" => get_balances(&state, params),
+        // The wallet has no accounts of its own, so the tests name accounts by address or alias
+        "accounts.list" => Ok(json!(AccountsListResponse {
+            accounts: vec![],
+            total: 0,
+        })),
+        _ => Err((METHOD_NOT_FOUND, format!("method '{}' not found", method))),
+    };
+    response(request, result)
+}
+
+fn method_and_params(request: &Value) -> (&str, Value) {
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    (method, params)
+}
+
+/// The JSON-RPC response to `request`.
+fn response(request: &Value, result: Result<Value, (i64, String)>) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    }
+}
+
+fn submit_instruction(state: &mut State, params: Value) -> Result<Value, (i64, String)> {
+    let request: CallInstructionRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let scripted = state.script.pop_front().unwrap_or_else(Scripted::accepted);
+
+    state.next_transaction += 1;
+    let mut id = [0u8; 32];
+    id[24..].copy_from_slice(&state.next_transaction.to_be_bytes());
+    let transaction_id = TransactionId::new(id);
+
+    // Dry runs are answered straight away, like the real daemon does
+    let response = if request.is_dry_run {
+        TransactionSubmitResponse {
+            transaction_id,
+            inputs: request.inputs.clone(),
+            result: Some(ExecuteResult {
+                finalize: scripted.result,
+            }),
+            json_result: Some(scripted.json_result),
+        }
+    } else {
+        state.pending.insert(transaction_id, scripted);
+        TransactionSubmitResponse {
+            transaction_id,
+            inputs: request.inputs.clone(),
+            result: None,
+            json_result: None,
+        }
+    };
+    state.requests.push(request);
+    Ok(json!(response))
+}
+
+fn wait_result(state: &mut State, params: Value) -> Result<Value, (i64, String)> {
+    let request: TransactionWaitResultRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let scripted = state
+        .pending
+        .get(&request.transaction_id)
+        .ok_or_else(|| (INVALID_PARAMS, "unknown transaction".to_string()))?;
+
+    // The real daemon holds the request for up to `timeout_secs`, the mock answers at once
+    if scripted.pending {
+        return Ok(json!(TransactionWaitResultResponse {
+            transaction_id: request.transaction_id,
+            status: scripted.status(),
+            final_fee: Amount::zero(),
+            result: None,
+            json_result: None,
+            timed_out: true,
+        }));
+    }
+    Ok(json!(TransactionWaitResultResponse {
+        transaction_id: request.transaction_id,
+        status: scripted.status(),
+        final_fee: Amount::new(scripted.fee as i64),
+        result: Some(scripted.result.clone()),
+        json_result: Some(scripted.json_result.clone()),
+        timed_out: false,
+    }))
+}
+
+fn get_balances(state: &State, params: Value) -> Result<Value, (i64, String)> {
+    let request: AccountsGetBalancesRequest =
+        serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    let address = match request.account {
+        ComponentAddressOrName::ComponentAddress(address) => address,
+        // Every named account is the same account to the mock
+        ComponentAddressOrName::Name(_) => format!("component_{}", "aa".repeat(32))
+            .parse::<ComponentAddress>()
+            .unwrap(),
+    };
+    Ok(json!(AccountsGetBalancesResponse {
+        address: SubstateAddress::Component(address),
+        balances: state.balances.clone(),
+    }))
+}
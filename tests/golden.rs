@@ -0,0 +1,379 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Compares the transactions built for the security-critical commands with the checked-in files
+//! in `tests/golden`. Any change to the proofs, workspace keys or inputs of these transactions
+//! changes a golden file and so shows up in review.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the files after an intended change.
+
+mod common;
+
+use common::component;
+use common::resource;
+use common::vault;
+use common::MockDaemon;
+use common::MockIndexer;
+use common::AUTH_TOKEN;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tari_engine_types::instruction::Instruction;
+use tari_engine_types::substate::SubstateAddress;
+use tari_stable_coin_example::config::Deployment;
+use tari_stable_coin_example::users::account_badge;
+use tari_stable_coin_example::DaemonClient;
+use tari_stable_coin_example::IndexerClient;
+use tari_stable_coin_example::StablecoinClient;
+use tari_template_lib::args;
+use tari_template_lib::args::Arg;
+use tari_template_lib::models::NonFungibleAddress;
+use tari_template_lib::prelude::ComponentAddress;
+use tari_template_lib::prelude::NonFungibleId;
+use tari_template_lib::prelude::ResourceAddress;
+use tari_template_lib::prelude::VaultId;
+use tari_wallet_daemon_client::types::CallInstructionRequest;
+use tari_wallet_daemon_client::ComponentAddressOrName;
+
+const AMOUNT: u64 = 250;
+const USER_ID: u64 = 7;
+
+/// The fixed inputs every transaction is built from.
+struct Fixture {
+    coin_component: ComponentAddress,
+    admin_badge_resource: ResourceAddress,
+    user_badge_resource: ResourceAddress,
+    coin_resource: ResourceAddress,
+    admin_account: ComponentAddress,
+    alice_account: ComponentAddress,
+    bob_account: ComponentAddress,
+    user_badge_vault: VaultId,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self {
+            coin_component: component(0x01),
+            admin_badge_resource: resource(0x02),
+            user_badge_resource: resource(0x03),
+            coin_resource: resource(0x04),
+            admin_account: component(0xa1),
+            alice_account: component(0xa2),
+            bob_account: component(0xa3),
+            user_badge_vault: vault(0xb1),
+        }
+    }
+
+    fn deployment(&self) -> Deployment {
+        Deployment {
+            template: None,
+            coin_component: Some(self.coin_component.to_string()),
+            admin_badge_resource: Some(self.admin_badge_resource.to_string()),
+            user_badge_resource: Some(self.user_badge_resource.to_string()),
+            coin_resource: Some(self.coin_resource.to_string()),
+        }
+    }
+
+    /// Names for every value of the fixture, so golden files read as `coin_component` rather
+    /// than as a hex string.
+    fn names(&self) -> Names {
+        let mut names = Names::default();
+        names.add_address(self.coin_component, "coin_component");
+        names.add_address(self.admin_account, "admin_account");
+        names.add_address(self.alice_account, "alice_account");
+        names.add_address(self.bob_account, "bob_account");
+        names.add_substate(
+            SubstateAddress::Component(self.coin_component),
+            "coin_component",
+        );
+        names.add_substate(
+            SubstateAddress::Vault(self.user_badge_vault),
+            "user_badge_vault",
+        );
+        names.add_substate(
+            SubstateAddress::NonFungible(NonFungibleAddress::new(
+                self.user_badge_resource,
+                NonFungibleId::from_u64(USER_ID),
+            )),
+            &format!("user_badge_resource/{}", USER_ID),
+        );
+        names.add_arg(args![self.admin_badge_resource], "admin_badge_resource");
+        names.add_arg(args![self.user_badge_resource], "user_badge_resource");
+        names.add_arg(args![self.coin_resource], "coin_resource");
+        names.add_arg(args![self.user_badge_vault], "user_badge_vault");
+        names.add_arg(args![AMOUNT], &AMOUNT.to_string());
+        names.add_arg(args![2 * AMOUNT], &(2 * AMOUNT).to_string());
+        names.add_arg(args![USER_ID], &USER_ID.to_string());
+        names
+    }
+}
+
+#[derive(Default)]
+struct Names {
+    values: HashMap<String, String>,
+}
+
+impl Names {
+    fn add_address(&mut self, address: ComponentAddress, name: &str) {
+        self.values.insert(address.to_string(), name.to_string());
+    }
+
+    fn add_substate(&mut self, address: SubstateAddress, name: &str) {
+        self.values.insert(address.to_string(), name.to_string());
+    }
+
+    /// Literal arguments are compared by their debug representation, which includes the
+    /// encoded bytes.
+    fn add_arg(&mut self, args: Vec<Arg>, name: &str) {
+        let [arg] = &args[..] else {
+            panic!("expected a single argument for {}", name);
+        };
+        self.values.insert(format!("{:?}", arg), name.to_string());
+    }
+
+    fn name<T: ToString>(&self, value: &T) -> String {
+        let value = value.to_string();
+        self.values.get(&value).cloned().unwrap_or(value)
+    }
+
+    fn arg(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::Workspace(key) => format!("${}", String::from_utf8_lossy(key)),
+            Arg::Literal(_) => {
+                let debug = format!("{:?}", arg);
+                self.values.get(&debug).cloned().unwrap_or(debug)
+            }
+        }
+    }
+
+    fn args(&self, args: &[Arg]) -> String {
+        args.iter()
+            .map(|arg| self.arg(arg))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn account(&self, account: &ComponentAddressOrName) -> String {
+        match account {
+            ComponentAddressOrName::Name(name) => name.clone(),
+            ComponentAddressOrName::ComponentAddress(address) => self.name(address),
+        }
+    }
+}
+
+/// Renders the parts of a request that decide what the transaction may touch and do.
+fn render(request: &CallInstructionRequest, names: &Names) -> String {
+    let mut out = String::new();
+    writeln!(out, "fee_account: {}", names.account(&request.fee_account)).unwrap();
+    writeln!(
+        out,
+        "dump_outputs_into: {}",
+        request
+            .dump_outputs_into
+            .as_ref()
+            .map(|account| names.account(account))
+            .unwrap_or_else(|| "none".to_string())
+    )
+    .unwrap();
+    writeln!(out, "max_fee: {}", request.max_fee).unwrap();
+    writeln!(out, "is_dry_run: {}", request.is_dry_run).unwrap();
+
+    writeln!(out, "inputs:").unwrap();
+    if request.inputs.is_empty() {
+        writeln!(out, "  (none)").unwrap();
+    }
+    for input in &request.inputs {
+        match input.version() {
+            Some(version) => writeln!(out, "  {}:{}", names.name(input.address()), version),
+            None => writeln!(out, "  {}", names.name(input.address())),
+        }
+        .unwrap();
+    }
+
+    writeln!(out, "instructions:").unwrap();
+    for instruction in &request.instructions {
+        let line = match instruction {
+            Instruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } => format!(
+                "{}.{}({})",
+                names.name(component_address),
+                method,
+                names.args(args)
+            ),
+            Instruction::CallFunction {
+                template_address,
+                function,
+                args,
+            } => format!(
+                "template {}::{}({})",
+                template_address,
+                function,
+                names.args(args)
+            ),
+            Instruction::PutLastInstructionOutputOnWorkspace { key } => format!(
+                "put_last_instruction_output_on_workspace(\"{}\")",
+                String::from_utf8_lossy(key)
+            ),
+            Instruction::DropAllProofsInWorkspace => "drop_all_proofs_in_workspace".to_string(),
+            other => format!("{:?}", other),
+        };
+        writeln!(out, "  {}", line).unwrap();
+    }
+    out
+}
+
+fn check_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("could not read {}: {}", path.display(), e));
+    assert_eq!(
+        actual,
+        expected,
+        "the transaction for {} differs from {}; if the change is intended, rerun with \
+         UPDATE_GOLDEN=1 and review the diff",
+        name,
+        path.display()
+    );
+}
+
+/// Starts a mock daemon and a client for the fixture's deployment.
+async fn client(fixture: &Fixture) -> (MockDaemon, StablecoinClient) {
+    let daemon = MockDaemon::start().await;
+    let client = DaemonClient::new(
+        daemon.endpoint().to_string(),
+        Some(AUTH_TOKEN.to_string()),
+        "TestAccount_0".to_string(),
+        Some(Duration::from_secs(5)),
+    );
+    let client = StablecoinClient::new(client, fixture.deployment()).with_max_fee(1500);
+    (daemon, client)
+}
+
+fn submitted(daemon: &MockDaemon, fixture: &Fixture) -> String {
+    let requests = daemon.requests();
+    assert_eq!(requests.len(), 1);
+    render(&requests[0], &fixture.names())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn increase_supply() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client.mint(fixture.admin_account, AMOUNT).await.unwrap();
+    check_golden("increase_supply", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn withdraw() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .issue(fixture.admin_account, fixture.alice_account, AMOUNT)
+        .await
+        .unwrap();
+    check_golden("withdraw", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mint_and_distribute() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .mint_and_distribute(
+            fixture.admin_account,
+            &[
+                (fixture.alice_account, AMOUNT),
+                (fixture.bob_account, AMOUNT),
+            ],
+        )
+        .await
+        .unwrap();
+    check_golden("mint_and_distribute", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn send() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .transfer(fixture.alice_account, fixture.bob_account, AMOUNT)
+        .await
+        .unwrap();
+    check_golden("send", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_new_admin() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .create_admin(fixture.admin_account, fixture.bob_account)
+        .await
+        .unwrap();
+    check_golden("create_new_admin", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_new_user() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .create_user(fixture.admin_account, USER_ID, fixture.alice_account)
+        .await
+        .unwrap();
+    check_golden("create_new_user", &submitted(&daemon, &fixture));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklist_user() {
+    let fixture = Fixture::new();
+    let (daemon, mut client) = client(&fixture).await;
+    client
+        .blacklist(fixture.admin_account, fixture.user_badge_vault, USER_ID)
+        .await
+        .unwrap();
+    check_golden("blacklist_user", &submitted(&daemon, &fixture));
+}
+
+/// `blacklist-user --account` looks up the badge and its vault, and must then build the same
+/// transaction as naming them.
+#[tokio::test(flavor = "multi_thread")]
+async fn blacklist_user_by_account() {
+    let fixture = Fixture::new();
+    let indexer = MockIndexer::start().await;
+    indexer.add_fungible_vault(
+        fixture.alice_account,
+        vault(0xc2),
+        fixture.coin_resource,
+        100,
+    );
+    indexer.add_non_fungible_vault(
+        fixture.alice_account,
+        fixture.user_badge_vault,
+        fixture.user_badge_resource,
+        &[USER_ID],
+    );
+    let (daemon, mut client) = client(&fixture).await;
+
+    let indexer = IndexerClient::new(indexer.endpoint().to_string());
+    let badge = account_badge(&indexer, client.deployment(), fixture.alice_account)
+        .await
+        .unwrap();
+    assert_eq!(badge.coin_vault, Some(vault(0xc2)));
+    client
+        .blacklist(fixture.admin_account, badge.badge_vault, badge.user_id)
+        .await
+        .unwrap();
+    check_golden("blacklist_user", &submitted(&daemon, &fixture));
+}
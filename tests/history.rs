@@ -0,0 +1,104 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Checks that the coin movements `history` reports are read correctly from the transactions
+//! the stablecoin commands build.
+
+mod common;
+
+use common::component;
+use common::resource;
+use tari_stable_coin_example::history::coin_movements;
+use tari_stable_coin_example::history::CoinMovement;
+use tari_stable_coin_example::history::MovementKind;
+use tari_template_lib::args;
+use tari_transaction::Transaction;
+
+#[test]
+fn transfer_moves_coins_between_accounts() {
+    let (coin_component, coin_resource) = (component(0x01), resource(0x04));
+    let (alice, bob) = (component(0xa2), component(0xa3));
+    let instructions = Transaction::builder()
+        .create_proof(alice, resource(0x03))
+        .put_last_instruction_output_on_workspace("proof")
+        .call_method(alice, "withdraw", args![coin_resource, 250u64])
+        .put_last_instruction_output_on_workspace("bucket")
+        .call_method(bob, "deposit", args![Variable("bucket"),])
+        .drop_all_proofs_in_workspace()
+        .build_as_instructions();
+
+    let movements = coin_movements(&instructions, &coin_component, &coin_resource);
+
+    assert_eq!(
+        movements,
+        vec![CoinMovement {
+            kind: MovementKind::Transfer,
+            from: Some(alice),
+            to: Some(bob),
+            amount: Some(250),
+        }]
+    );
+    assert!(movements[0].involves(&bob));
+    assert!(!movements[0].involves(&coin_component));
+}
+
+#[test]
+fn issue_moves_coins_out_of_the_coin_component() {
+    let (coin_component, coin_resource) = (component(0x01), resource(0x04));
+    let (admin, alice) = (component(0xa1), component(0xa2));
+    let instructions = Transaction::builder()
+        .create_proof(admin, resource(0x02))
+        .put_last_instruction_output_on_workspace("proof")
+        .call_method(coin_component, "withdraw", args![100u64])
+        .put_last_instruction_output_on_workspace("bucket")
+        .call_method(alice, "deposit", args![Variable("bucket"),])
+        .drop_all_proofs_in_workspace()
+        .build_as_instructions();
+
+    let movements = coin_movements(&instructions, &coin_component, &coin_resource);
+
+    assert_eq!(
+        movements,
+        vec![CoinMovement {
+            kind: MovementKind::Issue,
+            from: Some(coin_component),
+            to: Some(alice),
+            amount: Some(100),
+        }]
+    );
+}
+
+#[test]
+fn other_coin_component_calls_are_listed_without_an_amount() {
+    let (coin_component, coin_resource) = (component(0x01), resource(0x04));
+    let instructions = Transaction::builder()
+        .call_method(coin_component, "increase_supply", args![1000u64])
+        .call_method(coin_component, "create_new_admin", args![])
+        .build_as_instructions();
+
+    let kinds: Vec<_> = coin_movements(&instructions, &coin_component, &coin_resource)
+        .into_iter()
+        .map(|movement| (movement.kind, movement.amount))
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (MovementKind::Mint, Some(1000)),
+            (MovementKind::Call("create_new_admin".to_string()), None),
+        ]
+    );
+}
+
+#[test]
+fn transactions_of_other_resources_have_no_movements() {
+    let (coin_component, coin_resource) = (component(0x01), resource(0x04));
+    let (alice, bob) = (component(0xa2), component(0xa3));
+    let instructions = Transaction::builder()
+        .call_method(alice, "withdraw", args![resource(0x09), 5u64])
+        .put_last_instruction_output_on_workspace("bucket")
+        .call_method(bob, "deposit", args![Variable("bucket"),])
+        .build_as_instructions();
+
+    assert!(coin_movements(&instructions, &coin_component, &coin_resource).is_empty());
+}
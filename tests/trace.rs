@@ -0,0 +1,258 @@
+//  Copyright 2023 The Tari Project
+//  SPDX-License-Identifier: BSD-3-Clause
+
+//! Checks how coins are followed through the vault versions on a mock indexer, and how a trace
+//! is rendered as a tree and as a graph.
+
+mod common;
+
+use common::component;
+use common::resource;
+use common::transaction;
+use common::vault;
+use common::MockIndexer;
+use tari_stable_coin_example::trace::transaction_hex;
+use tari_stable_coin_example::trace::Direction;
+use tari_stable_coin_example::trace::Link;
+use tari_stable_coin_example::trace::Trace;
+use tari_stable_coin_example::trace::TracedTransaction;
+use tari_stable_coin_example::trace::Tracer;
+use tari_stable_coin_example::trace::VaultChange;
+use tari_stable_coin_example::IndexerClient;
+use tari_template_lib::prelude::VaultId;
+use tari_transaction::TransactionId;
+
+/// Alice is paid 100 in the first transaction and sends 40 of it to Bob in the second.
+fn payment_trace() -> Trace {
+    let (alice, bob, treasury) = (vault(0xa2), vault(0xa3), vault(0x01));
+    Trace {
+        roots: vec![transaction(2)],
+        transactions: vec![
+            TracedTransaction {
+                transaction_id: transaction(2),
+                depth: 0,
+                changes: vec![
+                    VaultChange {
+                        vault: alice,
+                        version: 2,
+                        previous_version: Some(1),
+                        change: -40,
+                    },
+                    VaultChange {
+                        vault: bob,
+                        version: 0,
+                        previous_version: None,
+                        change: 40,
+                    },
+                ],
+            },
+            TracedTransaction {
+                transaction_id: transaction(1),
+                depth: 1,
+                changes: vec![
+                    VaultChange {
+                        vault: treasury,
+                        version: 5,
+                        previous_version: Some(4),
+                        change: -100,
+                    },
+                    VaultChange {
+                        vault: alice,
+                        version: 1,
+                        previous_version: Some(0),
+                        change: 100,
+                    },
+                ],
+            },
+        ],
+        links: vec![Link {
+            from: transaction(1),
+            to: transaction(2),
+            vault: alice,
+        }],
+    }
+}
+
+fn label(vault: &VaultId) -> String {
+    if *vault == self::vault(0xa2) {
+        "alice".to_string()
+    } else {
+        vault.to_string()
+    }
+}
+
+#[test]
+fn tree_shows_where_the_coins_came_from() {
+    let lines = payment_trace().tree(label, |amount| amount.to_string());
+
+    assert_eq!(
+        lines[0],
+        format!("transaction {}", transaction_hex(&transaction(2)))
+    );
+    assert_eq!(lines[1], "  -40 alice");
+    assert_eq!(lines[2], format!("  +40 {}", vault(0xa3)));
+    assert_eq!(
+        lines[3],
+        format!(
+            "  funded via alice by transaction {}",
+            transaction_hex(&transaction(1))
+        )
+    );
+    assert_eq!(lines[4], format!("    -100 {}", vault(0x01)));
+    assert_eq!(lines[5], "    +100 alice");
+    assert_eq!(lines.len(), 6);
+}
+
+#[test]
+fn graph_has_withdrawals_into_and_deposits_out_of_transactions() {
+    let graph = payment_trace().graph(label, |amount| amount.to_string());
+
+    let nodes = graph["nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 5, "two transactions and three vaults");
+    let edges = graph["edges"].as_array().unwrap();
+    assert_eq!(edges[0]["from"], vault(0xa2).to_string());
+    assert_eq!(edges[0]["to"], transaction_hex(&transaction(2)));
+    assert_eq!(edges[0]["amount"], "40");
+    assert_eq!(edges[1]["from"], transaction_hex(&transaction(2)));
+    assert_eq!(edges[1]["to"], vault(0xa3).to_string());
+
+    let dot = payment_trace().dot(label, |amount| amount.to_string());
+    assert!(dot.starts_with("digraph trace {"));
+    assert!(dot.contains(&format!(
+        "\"{}\" -> \"{}\" [label=\"40\"];",
+        vault(0xa2),
+        transaction_hex(&transaction(2))
+    )));
+}
+
+/// Three deposits of 30, 20 and 25 into vault 0xc4, then 0x13 sends 40 of them to vault 0xc5
+/// (and 7 of another resource to 0xd4), of which 0x14 sends 15 back.
+async fn payments() -> MockIndexer {
+    let indexer = MockIndexer::start().await;
+    let (from, to, other) = (vault(0xc4), vault(0xc5), vault(0xd4));
+    indexer.add_fungible_vault(component(0xa4), from, resource(0x04), 0);
+    indexer.add_fungible_vault(component(0xa5), to, resource(0x04), 0);
+    indexer.add_fungible_vault(component(0xa4), other, resource(0x05), 0);
+    indexer.transact(transaction(0x10), &[(from, 30)]);
+    indexer.transact(transaction(0x11), &[(from, 20)]);
+    indexer.transact(transaction(0x12), &[(from, 25)]);
+    indexer.transact(transaction(0x13), &[(from, -40), (to, 40), (other, 7)]);
+    indexer.transact(transaction(0x14), &[(to, -15), (from, 15)]);
+    indexer
+}
+
+async fn trace(
+    indexer: &MockIndexer,
+    root: u8,
+    depth: u32,
+    direction: Direction,
+) -> (Vec<TransactionId>, Vec<Link>) {
+    let client = IndexerClient::new(indexer.endpoint().to_string());
+    let trace = Tracer::new(&client, resource(0x04))
+        .trace(vec![transaction(root)], depth, direction)
+        .await
+        .unwrap();
+    let reached = trace
+        .transactions
+        .iter()
+        .map(|tx| tx.transaction_id)
+        .collect();
+    (reached, trace.links)
+}
+
+fn link(from: u8, to: u8, vault: VaultId) -> Link {
+    Link {
+        from: transaction(from),
+        to: transaction(to),
+        vault,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_are_the_coin_balances_a_transaction_changed() {
+    let indexer = payments().await;
+    let client = IndexerClient::new(indexer.endpoint().to_string());
+    let changes = Tracer::new(&client, resource(0x04))
+        .changes(transaction(0x13))
+        .await
+        .unwrap();
+
+    let changes: Vec<_> = changes
+        .iter()
+        .map(|c| (c.vault, c.version, c.previous_version, c.change))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (vault(0xc4), 4, Some(3), -40),
+            (vault(0xc5), 1, Some(0), 40)
+        ],
+        "the vault of another resource is left out"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn withdrawals_are_funded_by_the_latest_deposits_that_cover_them() {
+    let indexer = payments().await;
+    let (reached, links) = trace(&indexer, 0x13, 1, Direction::Backward).await;
+
+    assert_eq!(
+        reached,
+        [transaction(0x13), transaction(0x12), transaction(0x11)],
+        "25 and 20 cover the 40 withdrawn, so the deposit of 30 is not needed"
+    );
+    assert_eq!(
+        links,
+        [link(0x11, 0x13, vault(0xc4)), link(0x12, 0x13, vault(0xc4))]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deposits_lead_to_the_next_withdrawal() {
+    let indexer = payments().await;
+    let (reached, links) = trace(&indexer, 0x13, 3, Direction::Forward).await;
+
+    assert_eq!(reached, [transaction(0x13), transaction(0x14)]);
+    assert_eq!(links, [link(0x13, 0x14, vault(0xc5))]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_stops_at_the_depth_and_keeps_to_the_direction() {
+    let indexer = payments().await;
+
+    let (reached, _) = trace(&indexer, 0x14, 1, Direction::Backward).await;
+    assert_eq!(reached, [transaction(0x14), transaction(0x13)]);
+
+    let (reached, _) = trace(&indexer, 0x14, 2, Direction::Backward).await;
+    assert_eq!(
+        reached,
+        [
+            transaction(0x14),
+            transaction(0x13),
+            transaction(0x12),
+            transaction(0x11)
+        ]
+    );
+
+    let (reached, _) = trace(&indexer, 0x14, 3, Direction::Forward).await;
+    assert_eq!(
+        reached,
+        [transaction(0x14)],
+        "nothing was withdrawn after 0x14"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recent_transactions_are_the_latest_versions_of_a_vault() {
+    let indexer = payments().await;
+    let client = IndexerClient::new(indexer.endpoint().to_string());
+    let recent = Tracer::new(&client, resource(0x04))
+        .recent_transactions(vault(0xc4), 3)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        recent,
+        [transaction(0x14), transaction(0x13), transaction(0x12)]
+    );
+}